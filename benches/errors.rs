@@ -0,0 +1,52 @@
+use std::hint::black_box;
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use criterion::{criterion_group, criterion_main, Criterion};
+use whynot_errors::AppError;
+
+/// The original two-field `AppError`, to compare the hot path against.
+struct BaselineError {
+    code: StatusCode,
+    message: String,
+}
+
+impl IntoResponse for BaselineError {
+    fn into_response(self) -> axum::response::Response {
+        (self.code, self.message).into_response()
+    }
+}
+
+fn baseline() -> BaselineError {
+    BaselineError {
+        code: StatusCode::NOT_FOUND,
+        message: "Not Found".to_string(),
+    }
+}
+
+fn construct(c: &mut Criterion) {
+    c.bench_function("baseline not_found", |b| b.iter(|| black_box(baseline())));
+
+    c.bench_function("not_found", |b| b.iter(|| black_box(AppError::not_found())));
+
+    c.bench_function("bad_request", |b| {
+        b.iter(|| black_box(AppError::bad_request(black_box("id must be a number"))))
+    });
+}
+
+fn into_response(c: &mut Criterion) {
+    c.bench_function("baseline not_found into_response", |b| {
+        b.iter(|| black_box(baseline().into_response()))
+    });
+
+    c.bench_function("not_found into_response", |b| {
+        b.iter(|| black_box(AppError::not_found().into_response()))
+    });
+
+    c.bench_function("not_found json into_response", |b| {
+        b.iter(|| black_box(AppError::not_found().json().into_response()))
+    });
+}
+
+criterion_group!(benches, construct, into_response);
+criterion_main!(benches);
@@ -1,64 +1,823 @@
+use std::backtrace::Backtrace;
+use std::borrow::Cow;
+use std::error::Error;
 use std::fmt::Display;
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::http::StatusCode;
+use axum::body::{Body, Bytes};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
 use axum::Json;
-use tracing::{error, warn};
+use futures_util::FutureExt;
+use serde::Serialize;
+
+use crate::config::{
+    default_error_code, json_error_serializer, max_message_len, redact_server_errors,
+};
+use crate::middleware::X_REQUEST_ID;
+
+const REDACTED_MESSAGE: &str = "Internal Server Error";
+
+/// Content type for plain text bodies.
+pub const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
 
 /// Global error type
 /// Use in basically all scenarios where an error is needed.
-#[derive(Debug)]
+#[derive(Clone)]
+#[must_use = "an AppError does nothing unless it is returned"]
 pub struct AppError {
     pub code: StatusCode,
-    pub message: String,
+    pub message: Cow<'static, str>,
+    pub(crate) location: Option<&'static Location<'static>>,
+    pub(crate) extras: Option<Box<Extras>>,
+}
+
+/// How bad an error is, going by its status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Anything below 400, for errors used to short circuit with a success or redirect.
+    Info,
+    /// A 4xx, the client got something wrong.
+    ClientError,
+    /// A 5xx, the server got something wrong.
+    ServerError,
+}
+
+impl Severity {
+    pub(crate) fn of(code: StatusCode) -> Self {
+        if code.is_server_error() {
+            Severity::ServerError
+        } else if code.is_client_error() {
+            Severity::ClientError
+        } else {
+            Severity::Info
+        }
+    }
+}
+
+/// The rarely used parts of an `AppError`, boxed so the common case stays small.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Extras {
+    pub(crate) source: Option<Arc<dyn Error + Send + Sync>>,
+    pub(crate) json: bool,
+    pub(crate) redact: bool,
+    pub(crate) error_code: Option<String>,
+    pub(crate) problem_type: Option<String>,
+    pub(crate) instance: Option<String>,
+    pub(crate) backtrace: Option<Arc<Backtrace>>,
+    pub(crate) headers: HeaderMap,
+    pub(crate) fields: Vec<(String, String)>,
+    pub(crate) field_errors: Vec<(String, String)>,
+    pub(crate) request_id: Option<String>,
+    pub(crate) debug_message: Option<String>,
+}
+
+/// The body written by `AppError` when rendering as JSON.
+#[derive(Serialize)]
+pub(crate) struct ErrorBody<'a> {
+    error: Cow<'a, str>,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Meta::is_empty")]
+    meta: Meta<'a>,
+    #[serde(skip_serializing_if = "FieldErrorsBody::is_empty")]
+    errors: FieldErrorsBody<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<&'a str>,
+}
+
+/// Serializes per-field errors as `{"field": ["message", ...]}`.
+struct FieldErrorsBody<'a>(&'a [(String, String)]);
+
+impl FieldErrorsBody<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for FieldErrorsBody<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut grouped: Vec<(&str, Vec<&str>)> = Vec::new();
+
+        for (field, message) in self.0 {
+            match grouped.iter_mut().find(|(name, _)| name == field) {
+                Some((_, messages)) => messages.push(message),
+                None => grouped.push((field, vec![message])),
+            }
+        }
+
+        serializer.collect_map(grouped)
+    }
+}
+
+/// Serializes the fields added with `with_field` as a JSON object, also what `{}` prints.
+pub(crate) struct Meta<'a>(pub(crate) &'a [(String, String)]);
+
+impl Meta<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for Meta<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl Display for Meta<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
 }
 
+/// `{}` prints `Code: 404; Not Found;`, the alternate `{:#}` prints `404 Not Found`.
 impl Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Code: {}; {};", self.code.as_u16(), self.message)
+        if f.alternate() {
+            write!(f, "{} {}", self.code.as_u16(), self.message)
+        } else {
+            write!(f, "Code: {}; {};", self.code.as_u16(), self.message)
+        }
+    }
+}
+
+/// Prints `AppError { code: 404 Not Found, message: "Not Found", .. }`, with the source, debug
+/// message and location when there are any.
+impl std::fmt::Debug for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("AppError");
+        debug
+            .field("code", &format_args!("{}", self.code))
+            .field("message", &self.message);
+
+        if let Some(source) = self.extras().and_then(|extras| extras.source.as_ref()) {
+            debug.field("source", source);
+        }
+
+        if let Some(message) = self.debug_message() {
+            debug.field("debug_message", &message);
+        }
+
+        if let Some(location) = self.location {
+            debug.field("location", &format_args!("{}", location));
+        }
+
+        debug.finish_non_exhaustive()
+    }
+}
+
+/// Errors are equal when their code and message are, everything else is ignored.
+impl PartialEq for AppError {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.message == other.message
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.extras()?
+            .source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
     }
 }
 
 impl AppError {
-    /// Create a new `AppError` from any `ToString` with a code 500.
+    /// Create a new `AppError` from any `ToString` with a code 500, or whatever was set with
+    /// `set_default_error_code`.
     /// If you want to customize the code, use the `AppError::code` factory.
+    #[track_caller]
     pub fn new(obj: impl ToString) -> Self {
-        error!("Server Error {}", obj.to_string());
+        Self::create(default_error_code(), obj.to_string())
+    }
 
-        Self {
-            code: StatusCode::INTERNAL_SERVER_ERROR,
-            message: obj.to_string(),
-        }
+    /// Create an error with the default code from anything that implements `Display`. Same as `new`.
+    #[track_caller]
+    pub fn from_display(obj: impl Display) -> Self {
+        Self::new(obj)
     }
 
-    /// FIXME: Remove this prior to version 1
-    #[deprecated]
-    pub fn from(obj: impl ToString) -> Self {
-        Self {
-            code: StatusCode::INTERNAL_SERVER_ERROR,
-            message: obj.to_string(),
-        }
+    /// Create an error with the default code from an error, using its `Display` as the message and keeping it as the `source`.
+    #[track_caller]
+    pub fn from_error(err: impl Error + Send + Sync + 'static) -> Self {
+        Self::new(err.to_string()).caused_by(err)
     }
 
     /// Return a closure which will accept a ToString to generate an AppError
+    /// The location recorded is where `code` was called, not where the closure runs.
+    #[track_caller]
     pub fn code<T: ToString>(code: StatusCode) -> impl Fn(T) -> Self {
-        move |obj| {
-            warn!(code = code.as_u16(), message = obj.to_string(), "Error");
+        let location = Location::caller();
 
-            Self {
-                code,
-                message: obj.to_string(),
+        move |obj| Self {
+            location: Some(location),
+            ..Self::create(code, obj.to_string())
+        }
+    }
+
+    /// Create an error with the canonical reason of `code` as the message, `StatusCode::CONFLICT`
+    /// gives "Conflict".
+    #[track_caller]
+    pub fn status(code: StatusCode) -> Self {
+        Self::create(code, code.canonical_reason().unwrap_or_default())
+    }
+
+    /// Create an error from a raw status code, falling back to a 500 if `code` isn't a valid one.
+    #[track_caller]
+    pub fn from_u16(code: u16, msg: impl ToString) -> Self {
+        let code = StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        Self::create(code, msg.to_string())
+    }
+
+    /// Create a 500 with the given message. Unlike `new` this ignores `set_default_error_code`.
+    #[track_caller]
+    pub fn server_error(msg: impl ToString) -> Self {
+        Self::create(StatusCode::INTERNAL_SERVER_ERROR, msg.to_string())
+    }
+
+    /// Create a 400 with the given message.
+    #[track_caller]
+    pub fn bad_request(msg: impl ToString) -> Self {
+        Self::code(StatusCode::BAD_REQUEST)(msg)
+    }
+
+    /// Create a 401 with the message "Unauthorized".
+    #[track_caller]
+    pub fn unauthorized() -> Self {
+        Self::status(StatusCode::UNAUTHORIZED)
+    }
+
+    /// Create a 401 with the given message.
+    #[track_caller]
+    pub fn unauthorized_with(msg: impl ToString) -> Self {
+        Self::code(StatusCode::UNAUTHORIZED)(msg)
+    }
+
+    /// Create a 403 with the message "Forbidden".
+    #[track_caller]
+    pub fn forbidden() -> Self {
+        Self::status(StatusCode::FORBIDDEN)
+    }
+
+    /// Create a 403 with the given message.
+    #[track_caller]
+    pub fn forbidden_with(msg: impl ToString) -> Self {
+        Self::code(StatusCode::FORBIDDEN)(msg)
+    }
+
+    /// Create a 404 with the message "Not Found".
+    #[track_caller]
+    pub fn not_found() -> Self {
+        Self::status(StatusCode::NOT_FOUND)
+    }
+
+    /// Create a 404 with the given message.
+    #[track_caller]
+    pub fn not_found_with(msg: impl ToString) -> Self {
+        Self::code(StatusCode::NOT_FOUND)(msg)
+    }
+
+    /// Create a 409 with the message "Conflict".
+    #[track_caller]
+    pub fn conflict() -> Self {
+        Self::status(StatusCode::CONFLICT)
+    }
+
+    /// Create a 409 with the given message.
+    #[track_caller]
+    pub fn conflict_with(msg: impl ToString) -> Self {
+        Self::code(StatusCode::CONFLICT)(msg)
+    }
+
+    /// Create a 422 with the given message.
+    #[track_caller]
+    pub fn unprocessable_entity(msg: impl ToString) -> Self {
+        Self::code(StatusCode::UNPROCESSABLE_ENTITY)(msg)
+    }
+
+    /// Create a 429 with the message "Too Many Requests", telling the client to retry after
+    /// `retry_after`, rounded up to whole seconds.
+    #[track_caller]
+    pub fn too_many_requests(retry_after: Duration) -> Self {
+        Self::status(StatusCode::TOO_MANY_REQUESTS).retry_after(whole_seconds(retry_after))
+    }
+
+    /// Create a 503 with the message "Service Unavailable", with a `Retry-After` header if given
+    /// how long until the service is back.
+    #[track_caller]
+    pub fn service_unavailable(retry_after: Option<Duration>) -> Self {
+        let err = Self::status(StatusCode::SERVICE_UNAVAILABLE);
+
+        match retry_after {
+            Some(retry_after) => err.retry_after(whole_seconds(retry_after)),
+            None => err,
+        }
+    }
+
+    /// Create a new `AppError` which keeps the underlying error around as its `source`.
+    #[track_caller]
+    pub fn with_source(
+        code: StatusCode,
+        message: impl ToString,
+        source: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        Self::raw(code, message.to_string()).caused_by(source)
+    }
+
+    /// Change the status code, keeping everything else.
+    pub fn with_code(mut self, code: StatusCode) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Change the message, keeping everything else.
+    pub fn with_message(mut self, msg: impl ToString) -> Self {
+        self.message = msg.to_string().into();
+        self
+    }
+
+    /// Prepend `msg` to the message, keeping the code. `"loading user: Not Found"`
+    pub fn context(mut self, msg: impl ToString) -> Self {
+        self.message = format!("{}: {}", msg.to_string(), self.message).into();
+        self
+    }
+
+    /// Like `context`, but the original error is also kept as the `source`, so the chain shows the
+    /// error as the lower layer reported it.
+    pub fn wrap(self, context: impl ToString) -> Self {
+        let mut wrapped = self.clone().context(context);
+        wrapped.extras_mut().source = Some(Arc::new(self));
+        wrapped
+    }
+
+    /// The message followed by the messages of the source chain, `"top: middle: root"`. A source
+    /// is skipped when the message before it already is, or ends with, `": "` and its message, as
+    /// with `from_error`, `context` or `wrap`.
+    pub fn display_chain(&self) -> String {
+        let mut chain = self.message.to_string();
+        let mut previous = chain.clone();
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            let message = match err.downcast_ref::<AppError>() {
+                Some(app_error) => app_error.message.to_string(),
+                None => err.to_string(),
+            };
+
+            let repeated = previous == message
+                || previous
+                    .strip_suffix(message.as_str())
+                    .is_some_and(|rest| rest.ends_with(": "));
+
+            if !repeated {
+                chain.push_str(": ");
+                chain.push_str(&message);
             }
+
+            previous = message;
+            source = err.source();
+        }
+
+        chain
+    }
+
+    /// Edit the message in place, from middleware for example.
+    pub fn message_mut(&mut self) -> &mut String {
+        self.message.to_mut()
+    }
+
+    /// Replace the message in place, see `with_message` for the builder style version.
+    pub fn set_message(&mut self, msg: impl ToString) {
+        self.message = msg.to_string().into();
+    }
+
+    /// Panic unless the code is `code`, with a message showing the whole error. For tests.
+    #[track_caller]
+    pub fn expect_status(self, code: StatusCode) -> Self {
+        if self.code != code {
+            panic!("expected an error with status {}, got {:?}", code, self);
         }
+
+        self
+    }
+
+    /// The status code. Named `status_code` since `AppError::code` is the constructor; prefer this
+    /// and `message` over the fields, which may become private.
+    pub fn status_code(&self) -> StatusCode {
+        self.code
+    }
+
+    /// The message, as sent to clients before any redaction or truncation.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Attach a detailed message for operators. It is logged and shows up in `Debug`, but is never
+    /// sent to clients, which only see `message`.
+    pub fn with_debug(mut self, msg: impl ToString) -> Self {
+        self.extras_mut().debug_message = Some(msg.to_string());
+        self
+    }
+
+    /// The message set with `with_debug`, if any.
+    pub fn debug_message(&self) -> Option<&str> {
+        self.extras()?.debug_message.as_deref()
+    }
+
+    /// Attach a machine readable identifier, like `"EMAIL_TAKEN"`, which is included in JSON bodies.
+    pub fn with_code_id(mut self, id: impl ToString) -> Self {
+        self.extras_mut().error_code = Some(id.to_string());
+        self
+    }
+
+    /// Whether the code is a 4xx.
+    pub fn is_client_error(&self) -> bool {
+        self.code.is_client_error()
+    }
+
+    /// Whether the code is a 5xx.
+    pub fn is_server_error(&self) -> bool {
+        self.code.is_server_error()
+    }
+
+    /// Whether the code is a 2xx.
+    pub fn is_success(&self) -> bool {
+        self.code.is_success()
+    }
+
+    /// How bad the error is, going by the code. Decides the level it's logged at.
+    pub fn severity(&self) -> Severity {
+        Severity::of(self.code)
+    }
+
+    /// The identifier set with `with_code_id`, if any.
+    pub fn code_id(&self) -> Option<&str> {
+        self.extras()?.error_code.as_deref()
+    }
+
+    /// Where this error was created.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+
+    /// The backtrace captured when this error was created. Only server errors made through `new`
+    /// capture one, and only with the `backtrace` feature on and `RUST_BACKTRACE` set.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.extras()?.backtrace.as_deref()
+    }
+
+    /// Render this error as a JSON body (`{"error": "...", "status": 500}`) instead of plain text.
+    /// To compress large bodies, add the compression layer after (outside of) this crate's
+    /// middleware, so it sees the final body.
+    pub fn json(mut self) -> Self {
+        self.extras_mut().json = true;
+        self
+    }
+
+    /// Add a header to the response, like `WWW-Authenticate` on a 401.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.extras_mut().headers.append(name, value);
+        self
+    }
+
+    /// Set the `Retry-After` header. Anything other than a 429 becomes a 503.
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        if self.code != StatusCode::TOO_MANY_REQUESTS {
+            self.code = StatusCode::SERVICE_UNAVAILABLE;
+        }
+
+        self.with_header(RETRY_AFTER, HeaderValue::from(seconds))
+    }
+
+    /// The headers added with `with_header`.
+    pub fn headers(&self) -> Option<&HeaderMap> {
+        self.extras().map(|extras| &extras.headers)
+    }
+
+    pub(crate) fn response_headers(&self) -> HeaderMap {
+        let mut headers = self.headers().cloned().unwrap_or_default();
+
+        if let Some(value) = self
+            .request_id()
+            .and_then(|id| HeaderValue::from_str(id).ok())
+        {
+            headers.insert(X_REQUEST_ID, value);
+        }
+
+        headers
+    }
+
+    /// Attach the id of the request this error happened in. It's sent back in the `X-Request-Id`
+    /// header and JSON and problem details bodies. The `middleware::request_id` middleware does this for you.
+    pub fn with_request_id(mut self, id: impl ToString) -> Self {
+        self.extras_mut().request_id = Some(id.to_string());
+        self
+    }
+
+    /// The id set with `with_request_id`, if any.
+    pub fn request_id(&self) -> Option<&str> {
+        self.extras()?.request_id.as_deref()
+    }
+
+    /// Attach a structured field, like a `user_id`, which is logged and included in JSON bodies under `meta`.
+    pub fn with_field(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.extras_mut()
+            .fields
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// The fields added with `with_field`.
+    pub fn fields(&self) -> &[(String, String)] {
+        self.extras().map_or(&[], |extras| &extras.fields)
+    }
+
+    /// Add an error for a single input field, included in JSON bodies under `errors`, grouped by
+    /// field, and in problem details as an `errors` array of `{"field", "message"}` objects.
+    pub fn with_field_error(mut self, field: impl ToString, message: impl ToString) -> Self {
+        self.extras_mut()
+            .field_errors
+            .push((field.to_string(), message.to_string()));
+        self
+    }
+
+    /// The errors added for individual input fields.
+    pub fn field_errors(&self) -> &[(String, String)] {
+        self.extras().map_or(&[], |extras| &extras.field_errors)
+    }
+
+    /// Hide the message of this error from clients if it is a 5xx, see `set_redact_server_errors`.
+    /// The real message is still logged.
+    pub fn redacted(mut self) -> Self {
+        self.extras_mut().redact = true;
+        self
+    }
+
+    /// The message sent to clients, which is replaced by a generic one for redacted server errors
+    /// and cut down to the length set with `set_max_message_len`.
+    pub fn public_message(&self) -> Cow<'_, str> {
+        if self.is_server_error()
+            && (self.extras().is_some_and(|extras| extras.redact) || redact_server_errors())
+        {
+            return Cow::Borrowed(REDACTED_MESSAGE);
+        }
+
+        cap_message(&self.message, max_message_len())
+    }
+
+    /// Convert into a JSON response regardless of whether `json` was set.
+    pub fn into_json_response(self) -> Response {
+        self.respond_with(Self::json_response)
+    }
+
+    /// Convert into a response for a `HEAD` request, with the status and headers of `into_response`
+    /// but an empty body. `middleware::request_context` picks this for `HEAD` requests.
+    pub fn into_head_response(self) -> Response {
+        let (parts, _) = self.into_response().into_parts();
+        Response::from_parts(parts, Body::empty())
+    }
+
+    /// Render the response and read its body, so tests can check the status, headers and body
+    /// without a router or an executor.
+    pub fn to_http_response(self) -> axum::http::Response<Bytes> {
+        let (parts, body) = self.into_response().into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX)
+            .now_or_never()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+
+        axum::http::Response::from_parts(parts, body)
+    }
+
+    /// Writes the body up front so the response has a `Content-Length`. Nothing is compressed here,
+    /// put a compression layer outside of this crate's middleware to compress the final body.
+    pub(crate) fn json_response(&self) -> Response {
+        let body = serde_json::to_vec(&self.json_body()).unwrap_or_default();
+
+        (
+            self.code,
+            self.response_headers(),
+            [
+                (CONTENT_TYPE, HeaderValue::from_static("application/json")),
+                (CONTENT_LENGTH, HeaderValue::from(body.len())),
+            ],
+            body,
+        )
+            .into_response()
+    }
+
+    pub(crate) fn json_body(&self) -> serde_json::Value {
+        match json_error_serializer() {
+            Some(serialize) => serialize(self),
+            None => default_json_error(self),
+        }
+    }
+
+    pub(crate) fn error_body(&self) -> ErrorBody<'_> {
+        ErrorBody {
+            error: self.public_message(),
+            status: self.code.as_u16(),
+            error_code: self.code_id(),
+            meta: Meta(self.fields()),
+            errors: FieldErrorsBody(self.field_errors()),
+            request_id: self.request_id(),
+            instance: self.extras().and_then(|extras| extras.instance.as_deref()),
+        }
+    }
+
+    /// Headers are only merged in when the error has extras, keeping the common case about as cheap
+    /// as a bare `(code, message)`.
+    pub(crate) fn text_response(&self) -> Response {
+        let message = match self.public_message() {
+            // Cloning a static message is free, unlike `into_owned`.
+            Cow::Borrowed(message) if message == self.message => self.message.clone(),
+            message => Cow::Owned(message.into_owned()),
+        };
+        let mut response = (self.code, message).into_response();
+
+        let headers = response.headers_mut();
+
+        if self.extras.is_some() {
+            headers.extend(self.response_headers());
+        }
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(TEXT_PLAIN));
+        response
+    }
+
+    pub(crate) fn caused_by(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.extras_mut().source = Some(Arc::from(source.into()));
+        self
+    }
+
+    /// Create an error for a status which must not have a body, like 204 or 304.
+    /// The response will have no body and no `Content-Type`.
+    #[track_caller]
+    pub fn no_content(code: StatusCode) -> Self {
+        Self::raw(code, code.canonical_reason().unwrap_or_default())
+    }
+
+    /// Whether responses for this error carry a body.
+    fn has_body(&self) -> bool {
+        !matches!(self.code, StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED)
+    }
+
+    /// Create an error, capturing a backtrace for server errors. Logging happens when the error is
+    /// turned into a response, not here.
+    #[track_caller]
+    pub(crate) fn create(code: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+        let err = Self::raw(code, message);
+
+        if code.is_server_error() {
+            err.with_backtrace()
+        } else {
+            err
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn raw(code: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            location: Some(Location::caller()),
+            extras: None,
+        }
+    }
+
+    pub(crate) fn extras(&self) -> Option<&Extras> {
+        self.extras.as_deref()
+    }
+
+    pub(crate) fn extras_mut(&mut self) -> &mut Extras {
+        self.extras.get_or_insert_with(Box::default)
+    }
+
+    fn with_backtrace(mut self) -> Self {
+        if let Some(backtrace) = capture_backtrace() {
+            self.extras_mut().backtrace = Some(Arc::new(backtrace));
+        }
+
+        self
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (self.code, self.message).into_response()
+        self.report();
+        self.into_rendered_response()
     }
 }
 
+impl AppError {
+    /// Log the error and send it to the enabled integrations, as it's turned into a response.
+    pub(crate) fn report(&self) {
+        #[cfg(feature = "tracing")]
+        self.log();
+
+        #[cfg(feature = "sentry")]
+        self.capture();
+
+        #[cfg(feature = "otel")]
+        self.record_exception();
+    }
+
+    /// Render without logging or reporting, for middleware re-rendering an error which already was.
+    pub(crate) fn into_rendered_response(self) -> Response {
+        if !self.has_body() {
+            return (self.code, self.response_headers()).into_response();
+        }
+
+        let response = if self.extras().is_some_and(|extras| extras.json) {
+            self.json_response()
+        } else {
+            self.text_response()
+        };
+
+        self.attach_to(response)
+    }
+
+    /// Report the error and render it with `render`, for the `into_*_response` methods which pick
+    /// the format themselves.
+    pub(crate) fn respond_with(self, render: impl FnOnce(&Self) -> Response) -> Response {
+        self.report();
+        let response = render(&self);
+        self.attach_to(response)
+    }
+
+    /// Keep the error in the extensions of its response, so middleware can re-render it (see
+    /// `middleware::negotiate`).
+    pub(crate) fn attach_to(self, mut response: Response) -> Response {
+        response.extensions_mut().insert(RenderedError(self));
+
+        response
+    }
+}
+
+#[cfg(feature = "backtrace")]
+fn capture_backtrace() -> Option<Backtrace> {
+    let backtrace = Backtrace::capture();
+
+    match backtrace.status() {
+        std::backtrace::BacktraceStatus::Captured => Some(backtrace),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn capture_backtrace() -> Option<Backtrace> {
+    None
+}
+
+impl From<AppError> for Response {
+    fn from(err: AppError) -> Self {
+        err.into_response()
+    }
+}
+
+/// The original `AppError` behind a response, stored in the response extensions.
+#[derive(Clone)]
+pub(crate) struct RenderedError(pub(crate) AppError);
+
+/// Cut `message` down to at most `max` bytes, ending in `...` when there's room for it.
+pub(crate) fn cap_message(message: &str, max: usize) -> Cow<'_, str> {
+    if message.len() <= max {
+        Cow::Borrowed(message)
+    } else if max < 3 {
+        Cow::Borrowed(truncate(message, max))
+    } else {
+        Cow::Owned(format!("{}...", truncate(message, max - 3)))
+    }
+}
+
+/// Cut `s` down to at most `max` bytes, without splitting a character.
+pub(crate) fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &s[..end]
+}
+
+fn whole_seconds(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .saturating_add(u64::from(duration.subsec_nanos() > 0))
+}
+
 /// Use this for most functions that return a result
 pub type AppResult<T> = Result<T, AppError>;
 
@@ -66,16 +825,72 @@ pub type AppResult<T> = Result<T, AppError>;
 pub type JsonResult<T> = AppResult<Json<T>>;
 
 /// Shortcut to wrap a result in json. Will consume the input.
+#[must_use = "the result should be returned from the handler"]
 pub fn json_ok<T>(obj: T) -> JsonResult<T> {
     Ok(Json(obj))
 }
 
+/// Like `json_ok`, but serializes the value up front so a failure is a clear 500 here instead of
+/// an error deep in the response machinery.
+#[must_use = "the result should be returned from the handler"]
+#[track_caller]
+pub fn json_ok_checked<T: Serialize>(obj: T) -> JsonResult<T> {
+    match serde_json::to_vec(&obj) {
+        Ok(_) => Ok(Json(obj)),
+        Err(err) => Err(AppError::server_error(&err).caused_by(err)),
+    }
+}
+
+/// Like `json_ok`, but with a status other than 200, like a 201 for a created resource.
+#[must_use = "the result should be returned from the handler"]
+pub fn json_status<T>(code: StatusCode, obj: T) -> AppResult<(StatusCode, Json<T>)> {
+    Ok((code, Json(obj)))
+}
+
+/// Shortcut to return an error from a JSON endpoint. The error body will also be JSON.
+#[must_use = "the result should be returned from the handler"]
+#[track_caller]
+pub fn json_err<T>(code: StatusCode, msg: impl ToString) -> JsonResult<T> {
+    Err(AppError::code(code)(msg).json())
+}
+
+/// The JSON body used for error responses unless `set_json_error_serializer` is called. Handy for
+/// a custom serializer which only wraps or extends it.
+pub fn default_json_error(err: &AppError) -> serde_json::Value {
+    serde_json::to_value(err.error_body()).unwrap_or_default()
+}
+
 /// If you are returning HTML, use this.
 pub type HtmlResult = AppResult<Html<String>>;
 
 /// Shortcut to wrap a result in html. Will consume the input.
+#[must_use = "the result should be returned from the handler"]
 pub fn html_ok(s: impl ToString) -> HtmlResult {
-    Ok(Html(s.to_string()))
+    html_ok_raw(Html(s.to_string()))
+}
+
+/// Like `html_ok`, for a page which is already `Html`, so it isn't converted again.
+#[must_use = "the result should be returned from the handler"]
+pub fn html_ok_raw(html: Html<String>) -> HtmlResult {
+    Ok(html)
+}
+
+/// Like `html_ok`, for a static page which doesn't need to be copied into a `String`.
+#[must_use = "the result should be returned from the handler"]
+pub fn html_ok_static(s: &'static str) -> AppResult<Html<&'static str>> {
+    Ok(Html(s))
+}
+
+/// Like `html_ok`, but with a status other than 200.
+#[must_use = "the result should be returned from the handler"]
+pub fn html_status(code: StatusCode, s: impl ToString) -> AppResult<(StatusCode, Html<String>)> {
+    Ok((code, Html(s.to_string())))
+}
+
+/// Shortcut to wrap any axum body, like bytes, a stream or `(StatusCode, T)`, in an `AppResult`.
+#[must_use = "the result should be returned from the handler"]
+pub fn ok<T: IntoResponse>(body: T) -> AppResult<T> {
+    Ok(body)
 }
 
 #[cfg(test)]
@@ -84,12 +899,10 @@ mod tests {
 
     #[test]
     fn test_fmt() {
-        let err = AppError {
-            code: StatusCode::OK,
-            message: "ok".to_string(),
-        };
+        let err = AppError::raw(StatusCode::OK, "ok".to_string());
 
         assert_eq!(err.to_string(), "Code: 200; ok;");
+        assert_eq!(format!("{err:#}"), "200 ok");
     }
 
     /// Test the from method. It should make an error from any object that implements `Display`
@@ -108,6 +921,77 @@ mod tests {
         assert_eq!(resp.unwrap().to_string(), "hi");
     }
 
+    #[test]
+    fn test_json_ok_checked() {
+        assert_eq!(json_ok_checked(vec![1, 2]).unwrap().0, vec![1, 2]);
+
+        let mut bad = std::collections::HashMap::new();
+        bad.insert(vec![1], "keys must be strings");
+
+        let err = json_ok_checked(bad).unwrap_err();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_json_err() {
+        let resp: JsonResult<String> = json_err(StatusCode::BAD_REQUEST, "nope");
+        let res = resp.unwrap_err().into_response();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()["content-type"], "application/json");
+    }
+
+    #[test]
+    fn test_request_id() {
+        let err = AppError::not_found().with_request_id("abc123");
+        assert_eq!(
+            serde_json::to_value(err.error_body()).unwrap()["request_id"],
+            "abc123"
+        );
+
+        let res = err.into_response();
+        assert_eq!(res.headers()[X_REQUEST_ID], "abc123");
+    }
+
+    #[test]
+    fn test_into_response() {
+        let res: Response = AppError::not_found().into();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_from_display() {
+        let err = AppError::from_display(42);
+
+        assert_eq!(err, AppError::new("42"));
+    }
+
+    #[test]
+    fn test_from_error() {
+        let err = AppError::from_error(std::io::Error::other("disk full"));
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "disk full");
+        assert_eq!(err.source().unwrap().to_string(), "disk full");
+    }
+
+    #[tokio::test]
+    async fn test_no_content() {
+        let res = AppError::no_content(StatusCode::NOT_MODIFIED)
+            .json()
+            .into_response();
+
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(res.headers().get("content-type").is_none());
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
     #[test]
     fn test_traits() {
         assert_eq!(AppError::new("hi").message, "hi");
@@ -126,4 +1010,521 @@ mod tests {
         assert_eq!(e.code, StatusCode::METHOD_NOT_ALLOWED);
         assert_eq!(e.message, "hi");
     }
+
+    #[test]
+    fn test_source() {
+        let parse_err = "nope".parse::<u32>().unwrap_err();
+        let err = AppError::with_source(StatusCode::BAD_REQUEST, "bad number", parse_err.clone());
+
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "bad number");
+        assert_eq!(err.source().unwrap().to_string(), parse_err.to_string());
+        assert!(AppError::new("hi").source().is_none());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert_eq!(
+            AppError::not_found().with_field("id", 1),
+            AppError::not_found()
+        );
+        assert_ne!(AppError::not_found(), AppError::forbidden());
+        assert_ne!(AppError::new("a"), AppError::new("b"));
+    }
+
+    #[test]
+    fn test_clone() {
+        let parse_err = "nope".parse::<u32>().unwrap_err();
+        let err = AppError::with_source(StatusCode::BAD_REQUEST, "bad number", parse_err)
+            .with_code_id("BAD_NUMBER");
+        let cloned = err.clone();
+
+        assert_eq!(cloned.code, err.code);
+        assert_eq!(cloned.message, err.message);
+        assert_eq!(cloned.code_id(), Some("BAD_NUMBER"));
+        assert!(cloned.source().is_some());
+    }
+
+    #[test]
+    fn test_text_content_type() {
+        let res = AppError::not_found().into_response();
+
+        assert_eq!(res.headers()[CONTENT_TYPE], TEXT_PLAIN);
+        assert_eq!(res.headers().get_all(CONTENT_TYPE).iter().count(), 1);
+    }
+
+    #[test]
+    fn test_json_response() {
+        let plain = AppError::new("hi").into_response();
+        assert_eq!(plain.headers()["content-type"], "text/plain; charset=utf-8");
+
+        let json = AppError::code(StatusCode::NOT_FOUND)("missing")
+            .json()
+            .into_response();
+        assert_eq!(json.status(), StatusCode::NOT_FOUND);
+        assert_eq!(json.headers()["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_json_compression() {
+        use axum::body::Body;
+        use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+        use axum::http::Request;
+        use axum::routing::get;
+        use axum::Router;
+        use tower::ServiceExt;
+        use tower_http::compression::CompressionLayer;
+
+        let res = AppError::bad_request("x".repeat(100))
+            .json()
+            .into_response();
+        let len: usize = res.headers()[CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(len, body.len());
+
+        async fn handler() -> AppResult<()> {
+            Err(AppError::bad_request("x".repeat(100)).json())
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(CompressionLayer::new());
+        let req = Request::builder()
+            .uri("/")
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+    }
+
+    #[test]
+    fn test_default_json_error() {
+        let err = AppError::bad_request("no name").with_code_id("NO_NAME");
+
+        assert_eq!(
+            default_json_error(&err),
+            serde_json::json!({"error": "no name", "status": 400, "error_code": "NO_NAME"})
+        );
+    }
+
+    #[test]
+    fn test_static_message() {
+        let err = AppError::not_found();
+
+        assert!(matches!(err.message, Cow::Borrowed("Not Found")));
+        assert_eq!(err.message(), "Not Found");
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_with_debug() {
+        let err = AppError::server_error("Something went wrong")
+            .with_debug("pool timed out after 30s")
+            .json();
+
+        assert_eq!(err.debug_message(), Some("pool timed out after 30s"));
+        assert!(format!("{err:?}").contains("pool timed out after 30s"));
+        assert!(!serde_json::to_string(&err.error_body())
+            .unwrap()
+            .contains("pool timed out"));
+    }
+
+    #[test]
+    fn test_html_ok() {
+        assert_eq!(html_ok("<p>hi</p>").unwrap().0, "<p>hi</p>");
+        assert_eq!(
+            html_ok_raw(Html("<p>hi</p>".to_string())).unwrap().0,
+            "<p>hi</p>"
+        );
+
+        let response = html_ok_static("<p>hi</p>").into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+    }
+
+    #[test]
+    fn test_json_status() {
+        let response = json_status(StatusCode::CREATED, vec![1]).into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+        let response = html_status(StatusCode::ACCEPTED, "<p>queued</p>").into_response();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[test]
+    fn test_ok() {
+        let response = ok((StatusCode::CREATED, "made")).into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let err: AppResult<Vec<u8>> = Err(AppError::not_found());
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_from_u16() {
+        assert_eq!(
+            AppError::from_u16(404, "gone"),
+            AppError::code(StatusCode::NOT_FOUND)("gone")
+        );
+        assert_eq!(
+            AppError::from_u16(1000, "odd").code,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_with_message_constructors() {
+        let err = AppError::not_found_with("user 42 not found");
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "user 42 not found");
+
+        assert_eq!(
+            AppError::unauthorized_with("expired").code,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            AppError::forbidden_with("admins only").code,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(AppError::conflict_with("taken").code, StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_status() {
+        let err = AppError::status(StatusCode::CONFLICT);
+
+        assert_eq!(err, AppError::conflict());
+        assert_eq!(err.message, "Conflict");
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(AppError::new("boom").severity(), Severity::ServerError);
+        assert_eq!(AppError::not_found().severity(), Severity::ClientError);
+        assert_eq!(
+            AppError::no_content(StatusCode::NO_CONTENT).severity(),
+            Severity::Info
+        );
+
+        assert!(AppError::not_found().is_client_error());
+        assert!(!AppError::not_found().is_server_error());
+        assert!(AppError::no_content(StatusCode::NO_CONTENT).is_success());
+    }
+
+    #[test]
+    fn test_to_http_response() {
+        let response = AppError::bad_request("no name").to_http_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], TEXT_PLAIN);
+        assert_eq!(response.body(), "no name");
+    }
+
+    #[test]
+    fn test_with_code() {
+        let err = AppError::new("bad input")
+            .with_code(StatusCode::BAD_REQUEST)
+            .with_message("id must be a number");
+
+        assert_eq!(err, AppError::bad_request("id must be a number"));
+    }
+
+    #[test]
+    fn test_message_mut() {
+        let mut err = AppError::not_found();
+        err.message_mut().push_str(": user 1");
+        assert_eq!(err.message, "Not Found: user 1");
+
+        err.set_message("gone");
+        assert_eq!(err.message, "gone");
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut err = AppError::not_found();
+        err.location = None;
+
+        assert_eq!(
+            format!("{err:?}"),
+            r#"AppError { code: 404 Not Found, message: "Not Found", .. }"#
+        );
+    }
+
+    #[test]
+    fn test_expect_status() {
+        let err = AppError::conflict().expect_status(StatusCode::CONFLICT);
+        assert_eq!(err.message, "Conflict");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected an error with status 404 Not Found, got AppError { code: 409"
+    )]
+    fn test_expect_status_panics() {
+        let _ = AppError::conflict().expect_status(StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_context() {
+        let err = AppError::not_found().context("loading user");
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "loading user: Not Found");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let err = AppError::not_found().wrap("loading user");
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "loading user: Not Found");
+        assert_eq!(err.source().unwrap().to_string(), "Code: 404; Not Found;");
+    }
+
+    #[test]
+    fn test_display_chain() {
+        let io = std::io::Error::other("disk full");
+        let err = AppError::with_source(StatusCode::INTERNAL_SERVER_ERROR, "saving upload", io)
+            .wrap("POST /files");
+
+        assert_eq!(err.display_chain(), "POST /files: saving upload: disk full");
+        assert_eq!(
+            AppError::from_error(std::io::Error::other("gone")).display_chain(),
+            "gone"
+        );
+
+        let err = AppError::with_source(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "connection error",
+            std::io::Error::other("error"),
+        );
+        assert_eq!(err.display_chain(), "connection error: error");
+
+        let err = AppError::with_source(
+            StatusCode::GATEWAY_TIMEOUT,
+            "request timeout",
+            std::io::Error::other("timeout"),
+        );
+        assert_eq!(err.display_chain(), "request timeout: timeout");
+
+        let err = AppError::from_error(std::io::Error::other("timeout")).context("request");
+        assert_eq!(err.display_chain(), "request: timeout");
+    }
+
+    #[test]
+    fn test_code_id() {
+        let err = AppError::code(StatusCode::CONFLICT)("taken").with_code_id("EMAIL_TAKEN");
+        assert_eq!(err.code_id(), Some("EMAIL_TAKEN"));
+
+        assert_eq!(
+            serde_json::to_string(&err.error_body()).unwrap(),
+            r#"{"error":"taken","status":409,"error_code":"EMAIL_TAKEN"}"#
+        );
+        assert_eq!(AppError::new("hi").code_id(), None);
+    }
+
+    #[test]
+    fn test_backtrace() {
+        assert!(AppError::not_found().backtrace().is_none());
+
+        #[cfg(not(feature = "backtrace"))]
+        assert!(AppError::new("hi").backtrace().is_none());
+
+        let err = crate::app_error!(StatusCode::BAD_GATEWAY, "hi");
+        assert_eq!(
+            err.backtrace().is_some(),
+            AppError::new("hi").backtrace().is_some()
+        );
+        assert!(crate::app_error!(StatusCode::CONFLICT, "hi")
+            .backtrace()
+            .is_none());
+    }
+
+    #[test]
+    fn test_location() {
+        let line = line!() + 1;
+        let err = AppError::new("hi");
+
+        let location = err.location().unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+
+        let line = line!() + 1;
+        let r: AppResult<()> = Err("hi").map_err(AppError::code(StatusCode::BAD_REQUEST));
+        assert_eq!(r.unwrap_err().location().unwrap().line(), line);
+    }
+
+    #[test]
+    fn test_redacted() {
+        let err = AppError::new("password=hunter2").redacted();
+        assert_eq!(err.public_message(), "Internal Server Error");
+        assert_eq!(err.message, "password=hunter2");
+
+        let err = AppError::not_found().redacted();
+        assert_eq!(err.public_message(), "Not Found");
+    }
+
+    #[test]
+    fn test_size() {
+        // The code, message, location and boxed extras, everything optional lives in `Extras`.
+        // Six words, 48 bytes on 64-bit targets.
+        assert!(std::mem::size_of::<AppError>() <= 6 * std::mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdef", 3), "abc");
+        assert_eq!(truncate("éé", 3), "é");
+    }
+
+    #[test]
+    fn test_cap_message() {
+        assert_eq!(cap_message("abcdefghij", 5), "ab...");
+        assert_eq!(cap_message("abcdefghij", 10), "abcdefghij");
+        assert_eq!(cap_message("abcdefghij", 2), "ab");
+        assert_eq!(cap_message("ééé", 5), "é...");
+        assert_eq!(cap_message("ééé", 6), "ééé");
+    }
+
+    #[test]
+    fn test_named() {
+        let cases = [
+            (
+                AppError::server_error("boom"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "boom",
+            ),
+            (
+                AppError::bad_request("bad id"),
+                StatusCode::BAD_REQUEST,
+                "bad id",
+            ),
+            (
+                AppError::unauthorized(),
+                StatusCode::UNAUTHORIZED,
+                "Unauthorized",
+            ),
+            (AppError::forbidden(), StatusCode::FORBIDDEN, "Forbidden"),
+            (AppError::not_found(), StatusCode::NOT_FOUND, "Not Found"),
+            (AppError::conflict(), StatusCode::CONFLICT, "Conflict"),
+            (
+                AppError::unprocessable_entity("bad email"),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "bad email",
+            ),
+            (
+                AppError::too_many_requests(Duration::from_secs(1)),
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too Many Requests",
+            ),
+        ];
+
+        for (err, code, message) in cases {
+            assert_eq!(err.code, code);
+            assert_eq!(err.message, message);
+        }
+    }
+
+    #[test]
+    fn test_headers() {
+        let res = AppError::unauthorized()
+            .with_header(
+                axum::http::header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Bearer"),
+            )
+            .into_response();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()["www-authenticate"], "Bearer");
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let err = AppError::too_many_requests(Duration::from_millis(29_500));
+        assert_eq!(err.code, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.headers().unwrap()[RETRY_AFTER], "30");
+
+        let err = AppError::too_many_requests(Duration::MAX);
+        assert_eq!(
+            err.headers().unwrap()[RETRY_AFTER],
+            u64::MAX.to_string().as_str()
+        );
+
+        let err = AppError::service_unavailable(Some(Duration::from_secs(120)));
+        assert_eq!(err.code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.headers().unwrap()[RETRY_AFTER], "120");
+        assert!(!AppError::service_unavailable(None)
+            .to_http_response()
+            .headers()
+            .contains_key(RETRY_AFTER));
+
+        let res = AppError::new("down").retry_after(60).json().into_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()[RETRY_AFTER], "60");
+    }
+
+    #[test]
+    fn test_fields() {
+        let err = AppError::not_found()
+            .with_field("user_id", 42)
+            .with_field("request_id", "abc");
+
+        assert_eq!(
+            serde_json::to_value(err.error_body()).unwrap(),
+            serde_json::json!({
+                "error": "Not Found",
+                "status": 404,
+                "meta": {"user_id": "42", "request_id": "abc"},
+            })
+        );
+        assert!(AppError::not_found().fields().is_empty());
+        assert_eq!(
+            Meta(err.fields()).to_string(),
+            r#"{"user_id":"42","request_id":"abc"}"#
+        );
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let err = AppError::unprocessable_entity("invalid")
+            .with_field_error("email", "is required")
+            .with_field_error("name", "too short")
+            .with_field_error("email", "is invalid");
+
+        assert_eq!(
+            serde_json::to_value(err.error_body()).unwrap()["errors"],
+            serde_json::json!({
+                "email": ["is required", "is invalid"],
+                "name": ["too short"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_into_head_response() {
+        let res = AppError::too_many_requests(Duration::from_secs(5))
+            .json()
+            .into_head_response();
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "5");
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
 }
@@ -0,0 +1,163 @@
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::{AppError, AppResult};
+
+/// A collection of errors, for endpoints which report every failure instead of the first.
+/// Renders as a JSON array of error bodies, with the most severe status code of the lot and the
+/// headers of every error, like `Retry-After`.
+#[derive(Debug, Clone, Default)]
+pub struct AppErrors {
+    errors: Vec<AppError>,
+}
+
+impl AppErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, err: AppError) {
+        self.errors.push(err);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, AppError> {
+        self.errors.iter()
+    }
+
+    /// The highest status code of the collected errors, or a 500 when there are none.
+    pub fn code(&self) -> StatusCode {
+        self.errors
+            .iter()
+            .map(|err| err.code)
+            .max()
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// The headers of every error, those of the errors with the winning `code` taking precedence.
+    fn headers(&self, code: StatusCode) -> HeaderMap {
+        let (winners, others): (Vec<_>, Vec<_>) =
+            self.errors.iter().partition(|err| err.code == code);
+
+        let mut headers = HeaderMap::new();
+        for err in others.into_iter().chain(winners) {
+            headers.extend(err.response_headers());
+        }
+
+        headers
+    }
+}
+
+impl FromIterator<AppError> for AppErrors {
+    fn from_iter<I: IntoIterator<Item = AppError>>(iter: I) -> Self {
+        Self {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for AppErrors {
+    type Item = AppError;
+    type IntoIter = std::vec::IntoIter<AppError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+/// Collect every `Ok` value, or every error when any item failed. Unlike collecting into an
+/// `AppResult<Vec<T>>`, this doesn't stop at the first error, so batch endpoints can report them all.
+pub fn try_collect_app<I, T>(iter: I) -> Result<Vec<T>, AppErrors>
+where
+    I: IntoIterator<Item = AppResult<T>>,
+{
+    let mut values = Vec::new();
+    let mut errors = AppErrors::new();
+
+    for item in iter {
+        match item {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
+}
+
+impl IntoResponse for AppErrors {
+    fn into_response(self) -> Response {
+        self.errors.iter().for_each(AppError::report);
+
+        let code = self.code();
+        let bodies: Vec<_> = self.errors.iter().map(AppError::json_body).collect();
+
+        (code, self.headers(code), Json(bodies)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code() {
+        let mut errors = AppErrors::new();
+        assert_eq!(errors.code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        errors.push(AppError::bad_request("bad name"));
+        errors.push(AppError::unprocessable_entity("bad email"));
+        assert_eq!(errors.code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        errors.push(AppError::new("boom"));
+        assert_eq!(errors.code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_response() {
+        let errors: AppErrors = vec![AppError::bad_request("a"), AppError::not_found()]
+            .into_iter()
+            .collect();
+        let res = errors.into_response();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()["content-type"], "application/json");
+    }
+
+    #[test]
+    fn test_response_headers() {
+        let errors: AppErrors = vec![
+            AppError::bad_request("a"),
+            AppError::too_many_requests(std::time::Duration::from_secs(30)),
+        ]
+        .into_iter()
+        .collect();
+        let res = errors.into_response();
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()["retry-after"], "30");
+    }
+
+    #[test]
+    fn test_try_collect_app() {
+        let parse = |s: &str| s.parse::<u32>().map_err(|_| AppError::bad_request(s));
+
+        assert_eq!(try_collect_app(["1", "2"].map(parse)).unwrap(), [1, 2]);
+
+        let errors = try_collect_app(["1", "x", "2", "y"].map(parse)).unwrap_err();
+        let messages: Vec<_> = errors.iter().map(AppError::message).collect();
+        assert_eq!(messages, ["x", "y"]);
+    }
+}
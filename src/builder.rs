@@ -0,0 +1,95 @@
+use std::error::Error;
+
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+
+use crate::AppError;
+
+/// Fluent construction of an `AppError`, see `AppError::builder`.
+/// Without a `message` the reason phrase of the code is used, without a `code` it's a 500.
+#[derive(Debug)]
+pub struct AppErrorBuilder {
+    inner: AppError,
+    message: Option<String>,
+}
+
+impl AppError {
+    /// Start building an `AppError`.
+    #[track_caller]
+    pub fn builder() -> AppErrorBuilder {
+        AppErrorBuilder {
+            inner: AppError::raw(StatusCode::INTERNAL_SERVER_ERROR, String::new()),
+            message: None,
+        }
+    }
+}
+
+impl AppErrorBuilder {
+    pub fn code(mut self, code: StatusCode) -> Self {
+        self.inner.code = code;
+        self
+    }
+
+    pub fn message(mut self, message: impl ToString) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.inner = self.inner.with_header(name, value);
+        self
+    }
+
+    pub fn field(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.inner = self.inner.with_field(key, value);
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.inner = self.inner.caused_by(source);
+        self
+    }
+
+    pub fn build(self) -> AppError {
+        let mut err = self.inner;
+
+        err.message = match self.message {
+            Some(message) => message.into(),
+            None => err.code.canonical_reason().unwrap_or_default().into(),
+        };
+
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::WWW_AUTHENTICATE;
+
+    #[test]
+    fn test_builder() {
+        let err = AppError::builder()
+            .code(StatusCode::UNAUTHORIZED)
+            .message("token expired")
+            .header(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))
+            .field("user_id", 1)
+            .source(std::io::Error::other("expired"))
+            .build();
+
+        assert_eq!(err.code, StatusCode::UNAUTHORIZED);
+        assert_eq!(err.message, "token expired");
+        assert_eq!(err.headers().unwrap()[WWW_AUTHENTICATE], "Bearer");
+        assert_eq!(err.fields(), [("user_id".to_string(), "1".to_string())]);
+        assert_eq!(err.source().unwrap().to_string(), "expired");
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let err = AppError::builder().build();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "Internal Server Error");
+
+        let err = AppError::builder().code(StatusCode::CONFLICT).build();
+        assert_eq!(err.message, "Conflict");
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use axum::http::StatusCode;
+
+use crate::{AppError, Severity};
+
+/// Renders an `AppError` into an HTML page.
+pub type HtmlErrorRenderer = fn(&AppError) -> String;
+
+/// Builds the JSON body of an error response, see `set_json_error_serializer`.
+pub type JsonErrorSerializer = fn(&AppError) -> serde_json::Value;
+
+#[cfg(feature = "tracing")]
+static LOG_RESPONSES: AtomicBool = AtomicBool::new(true);
+static REDACT_SERVER_ERRORS: AtomicBool = AtomicBool::new(false);
+static DEFAULT_ERROR_CODE: AtomicU16 = AtomicU16::new(500);
+static MAX_MESSAGE_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+static HTML_ERROR_RENDERER: RwLock<Option<HtmlErrorRenderer>> = RwLock::new(None);
+static HTML_STATUS_RENDERERS: RwLock<Vec<(StatusCode, HtmlErrorRenderer)>> =
+    RwLock::new(Vec::new());
+static HTML_SEVERITY_RENDERERS: RwLock<Vec<(Severity, HtmlErrorRenderer)>> =
+    RwLock::new(Vec::new());
+static JSON_ERROR_SERIALIZER: RwLock<Option<JsonErrorSerializer>> = RwLock::new(None);
+
+/// Turn the logging of errors on or off, both when an `AppError` is converted into a response, by
+/// `into_response` or one of the `into_*_response` methods, and by `ResultExt::inspect_err_log`.
+/// On by default.
+#[cfg(feature = "tracing")]
+pub fn set_log_responses(enabled: bool) {
+    LOG_RESPONSES.store(enabled, Ordering::Relaxed);
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log_responses() -> bool {
+    LOG_RESPONSES.load(Ordering::Relaxed)
+}
+
+/// Replace the message of every 5xx response with a generic "Internal Server Error". Off by default.
+/// The real message is still logged.
+pub fn set_redact_server_errors(enabled: bool) {
+    REDACT_SERVER_ERRORS.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn redact_server_errors() -> bool {
+    REDACT_SERVER_ERRORS.load(Ordering::Relaxed)
+}
+
+/// Use `render` to build the page for HTML error responses, instead of the built in one.
+pub fn set_html_error_renderer(render: HtmlErrorRenderer) {
+    *HTML_ERROR_RENDERER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(render);
+}
+
+/// Use `render` for HTML error responses with the given code, like a branded 404 page. Takes
+/// precedence over `set_html_error_renderer_for_severity` and `set_html_error_renderer`.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use futures_util::FutureExt;
+/// use whynot_errors::{
+///     escape_html, set_html_error_renderer_for, set_html_error_renderer_for_severity, AppError,
+///     Severity,
+/// };
+///
+/// set_html_error_renderer_for_severity(Severity::ClientError, |_| "<p>Oops</p>".to_string());
+/// set_html_error_renderer_for(StatusCode::IM_A_TEAPOT, |err| {
+///     format!("<p>{} and stout</p>", escape_html(&err.message))
+/// });
+///
+/// let res = AppError::code(StatusCode::IM_A_TEAPOT)("short").into_html_response();
+/// let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+///     .now_or_never()
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(body, "<p>short and stout</p>");
+/// ```
+pub fn set_html_error_renderer_for(code: StatusCode, render: HtmlErrorRenderer) {
+    set_renderer(&HTML_STATUS_RENDERERS, code, render);
+}
+
+/// Use `render` for HTML error responses of a whole class, like every 5xx. Codes with a renderer
+/// from `set_html_error_renderer_for` still use that one, `set_html_error_renderer` stays the
+/// fallback for the rest.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use futures_util::FutureExt;
+/// use whynot_errors::{set_html_error_renderer_for_severity, AppError, Severity};
+///
+/// set_html_error_renderer_for_severity(Severity::ServerError, |_| "<p>Down</p>".to_string());
+///
+/// let res = AppError::code(StatusCode::BAD_GATEWAY)("no upstream").into_html_response();
+/// let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+///     .now_or_never()
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(body, "<p>Down</p>");
+/// ```
+pub fn set_html_error_renderer_for_severity(severity: Severity, render: HtmlErrorRenderer) {
+    set_renderer(&HTML_SEVERITY_RENDERERS, severity, render);
+}
+
+fn set_renderer<K: PartialEq>(
+    renderers: &RwLock<Vec<(K, HtmlErrorRenderer)>>,
+    key: K,
+    render: HtmlErrorRenderer,
+) {
+    let mut renderers = renderers
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    renderers.retain(|(existing, _)| *existing != key);
+    renderers.push((key, render));
+}
+
+/// The renderer set for `code`, then the one for its severity, then the fallback one.
+pub(crate) fn html_error_renderer(code: StatusCode) -> Option<HtmlErrorRenderer> {
+    find_renderer(&HTML_STATUS_RENDERERS, code)
+        .or_else(|| find_renderer(&HTML_SEVERITY_RENDERERS, Severity::of(code)))
+        .or_else(|| {
+            *HTML_ERROR_RENDERER
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        })
+}
+
+fn find_renderer<K: PartialEq>(
+    renderers: &RwLock<Vec<(K, HtmlErrorRenderer)>>,
+    key: K,
+) -> Option<HtmlErrorRenderer> {
+    renderers
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .find(|(existing, _)| *existing == key)
+        .map(|(_, render)| *render)
+}
+
+/// Use `serialize` to build the body of JSON error responses, instead of `default_json_error`.
+pub fn set_json_error_serializer(serialize: JsonErrorSerializer) {
+    *JSON_ERROR_SERIALIZER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(serialize);
+}
+
+pub(crate) fn json_error_serializer() -> Option<JsonErrorSerializer> {
+    *JSON_ERROR_SERIALIZER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Change the code used by `AppError::new` and the `From` conversions which don't pick their own.
+/// Defaults to 500.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::{set_default_error_code, AppError};
+///
+/// set_default_error_code(StatusCode::BAD_GATEWAY);
+///
+/// assert_eq!(AppError::new("upstream down").code, StatusCode::BAD_GATEWAY);
+///
+/// let err: AppError = std::io::Error::other("upstream down").into();
+/// assert_eq!(err.code, StatusCode::BAD_GATEWAY);
+///
+/// // Conversions which pick their own code are left alone.
+/// let err: AppError = String::from_utf8(vec![0xff]).unwrap_err().into();
+/// assert_eq!(err.code, StatusCode::BAD_REQUEST);
+///
+/// // And so are the explicit 500s.
+/// use whynot_errors::IntoAppResult;
+/// let err = Err::<(), _>("boom").into_app_500().unwrap_err();
+/// assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+/// ```
+pub fn set_default_error_code(code: StatusCode) {
+    DEFAULT_ERROR_CODE.store(code.as_u16(), Ordering::Relaxed);
+}
+
+pub(crate) fn default_error_code() -> StatusCode {
+    StatusCode::from_u16(DEFAULT_ERROR_CODE.load(Ordering::Relaxed))
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Cut messages sent to clients down to at most `len` bytes, ending in `...`. Unlimited by default.
+/// The full message is still logged.
+///
+/// ```
+/// use whynot_errors::{set_max_message_len, AppError};
+///
+/// set_max_message_len(5);
+///
+/// assert_eq!(AppError::bad_request("abcdefghij").public_message(), "ab...");
+/// ```
+pub fn set_max_message_len(len: usize) {
+    MAX_MESSAGE_LEN.store(len, Ordering::Relaxed);
+}
+
+pub(crate) fn max_message_len() -> usize {
+    MAX_MESSAGE_LEN.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_json_error, AppErrors};
+    use axum::response::IntoResponse;
+    use futures_util::FutureExt;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Held by tests which change a global hook, which restore it before letting go.
+    static HOOKS: Mutex<()> = Mutex::new(());
+
+    // Only errors with this id are serialized differently, so other tests running at the same
+    // time keep the default body.
+    fn serialize(err: &AppError) -> serde_json::Value {
+        match err.code_id() {
+            Some("CUSTOM_BODY") => json!({ "message": err.message() }),
+            _ => default_json_error(err),
+        }
+    }
+
+    #[test]
+    fn test_json_error_serializer() {
+        let _hooks = HOOKS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = json_error_serializer();
+        set_json_error_serializer(serialize);
+
+        let err = AppError::bad_request("bad name").with_code_id("CUSTOM_BODY");
+        let res = err.clone().json().to_http_response();
+        let single: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+
+        let errors: AppErrors = vec![err].into_iter().collect();
+        let body = axum::body::to_bytes(errors.into_response().into_body(), usize::MAX)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        let all: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        *JSON_ERROR_SERIALIZER
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = previous;
+
+        assert_eq!(single, json!({ "message": "bad name" }));
+        assert_eq!(all, json!([{ "message": "bad name" }]));
+    }
+}
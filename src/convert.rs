@@ -0,0 +1,682 @@
+use std::error::Error;
+use std::io;
+use std::num::ParseIntError;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::header::{InvalidHeaderName, InvalidHeaderValue};
+use axum::http::StatusCode;
+
+use crate::{AppError, SetupError};
+
+/// Map a domain error into an `AppError`. Implementing this gets you `From<T> for AppError`, so
+/// `?` and `map_err(AppError::from)` work.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::{AppError, ToAppError};
+///
+/// enum UserError {
+///     Missing(u32),
+///     Banned,
+/// }
+///
+/// impl ToAppError for UserError {
+///     fn to_app_error(self) -> AppError {
+///         match self {
+///             UserError::Missing(id) => AppError::code(StatusCode::NOT_FOUND)(format!("user {id} not found")),
+///             UserError::Banned => AppError::forbidden(),
+///         }
+///     }
+/// }
+///
+/// let err: AppError = UserError::Missing(4).into();
+/// assert_eq!(err.message, "user 4 not found");
+/// ```
+pub trait ToAppError {
+    fn to_app_error(self) -> AppError;
+}
+
+impl<T: ToAppError> From<T> for AppError {
+    #[track_caller]
+    fn from(err: T) -> Self {
+        err.to_app_error()
+    }
+}
+
+/// Uses the canonical reason phrase as the message, `StatusCode::FORBIDDEN` becomes "Forbidden".
+impl From<StatusCode> for AppError {
+    #[track_caller]
+    fn from(code: StatusCode) -> Self {
+        Self::status(code)
+    }
+}
+
+/// `(StatusCode::CONFLICT, "taken")` becomes an error with that code and message.
+impl From<(StatusCode, &str)> for AppError {
+    #[track_caller]
+    fn from((code, message): (StatusCode, &str)) -> Self {
+        Self::create(code, message.to_string())
+    }
+}
+
+impl From<(StatusCode, String)> for AppError {
+    #[track_caller]
+    fn from((code, message): (StatusCode, String)) -> Self {
+        Self::create(code, message)
+    }
+}
+
+/// Extractor rejections keep axum's status, 400 for a malformed body or 422 for one which doesn't
+/// match the type, and its message. Take the extractor as a `Result` in the handler, or use
+/// `WithRejection` from `axum-extra`, so the rejection goes through `AppError` like everything else.
+///
+/// ```
+/// use axum::extract::rejection::JsonRejection;
+/// use axum::Json;
+/// use whynot_errors::{json_ok, JsonResult};
+///
+/// async fn create(body: Result<Json<Vec<u32>>, JsonRejection>) -> JsonResult<usize> {
+///     let Json(ids) = body?;
+///     json_ok(ids.len())
+/// }
+/// ```
+impl From<JsonRejection> for AppError {
+    #[track_caller]
+    fn from(rejection: JsonRejection) -> Self {
+        Self::create(rejection.status(), rejection.body_text()).caused_by(rejection)
+    }
+}
+
+impl From<PathRejection> for AppError {
+    #[track_caller]
+    fn from(rejection: PathRejection) -> Self {
+        Self::create(rejection.status(), rejection.body_text()).caused_by(rejection)
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    #[track_caller]
+    fn from(rejection: QueryRejection) -> Self {
+        Self::create(rejection.status(), rejection.body_text()).caused_by(rejection)
+    }
+}
+
+impl From<Box<dyn Error + Send + Sync>> for AppError {
+    #[track_caller]
+    fn from(err: Box<dyn Error + Send + Sync>) -> Self {
+        Self::new(err.to_string()).caused_by(err)
+    }
+}
+
+impl From<io::Error> for AppError {
+    #[track_caller]
+    fn from(err: io::Error) -> Self {
+        Self::from_error(err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    #[track_caller]
+    fn from(err: serde_json::Error) -> Self {
+        Self::from_error(err)
+    }
+}
+
+impl From<ParseIntError> for AppError {
+    #[track_caller]
+    fn from(err: ParseIntError) -> Self {
+        Self::from_error(err)
+    }
+}
+
+/// Bad encoding comes from the client, so it's a 400.
+impl From<Utf8Error> for AppError {
+    #[track_caller]
+    fn from(err: Utf8Error) -> Self {
+        Self::bad_request(err).caused_by(err)
+    }
+}
+
+impl From<FromUtf8Error> for AppError {
+    #[track_caller]
+    fn from(err: FromUtf8Error) -> Self {
+        Self::bad_request(&err).caused_by(err)
+    }
+}
+
+/// Building a bad header is a bug on our side, so it's a 500.
+impl From<InvalidHeaderValue> for AppError {
+    #[track_caller]
+    fn from(err: InvalidHeaderValue) -> Self {
+        Self::server_error(&err).caused_by(err)
+    }
+}
+
+impl From<InvalidHeaderName> for AppError {
+    #[track_caller]
+    fn from(err: InvalidHeaderName) -> Self {
+        Self::server_error(&err).caused_by(err)
+    }
+}
+
+impl From<SetupError> for AppError {
+    #[track_caller]
+    fn from(err: SetupError) -> Self {
+        Self::new(&err.msg).caused_by(err)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl From<sqlx::Error> for AppError {
+    #[track_caller]
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => Self::not_found(),
+            err => Self::from_error(err),
+        }
+    }
+}
+
+/// Timeouts become 504 and failed connections 502. Errors for an upstream 4xx or 5xx pass that
+/// status on, anything else is a 500.
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for AppError {
+    #[track_caller]
+    fn from(err: reqwest::Error) -> Self {
+        let code = if err.is_timeout() {
+            StatusCode::GATEWAY_TIMEOUT
+        } else if err.is_connect() {
+            StatusCode::BAD_GATEWAY
+        } else {
+            match err.status() {
+                Some(status) if status.is_client_error() || status.is_server_error() => status,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        };
+
+        Self::create(code, err.to_string()).caused_by(err)
+    }
+}
+
+/// A task which panicked or was cancelled is a 500.
+#[cfg(feature = "tokio")]
+impl From<tokio::task::JoinError> for AppError {
+    #[track_caller]
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::server_error(&err).caused_by(err)
+    }
+}
+
+/// A `tokio::time::timeout` which ran out is a 504.
+#[cfg(feature = "tokio")]
+impl From<tokio::time::error::Elapsed> for AppError {
+    #[track_caller]
+    fn from(err: tokio::time::error::Elapsed) -> Self {
+        Self::create(StatusCode::GATEWAY_TIMEOUT, "request timed out").caused_by(err)
+    }
+}
+
+/// Bad, expired or not yet valid tokens are a 401 with a `WWW-Authenticate: Bearer` header. Errors
+/// from keys or signing are on our side, so they are a 500.
+#[cfg(feature = "jwt")]
+impl From<jsonwebtoken::errors::Error> for AppError {
+    #[track_caller]
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        use axum::http::header::WWW_AUTHENTICATE;
+        use axum::http::HeaderValue;
+        use jsonwebtoken::errors::ErrorKind;
+
+        let message = match err.kind() {
+            ErrorKind::ExpiredSignature => "Token has expired",
+            ErrorKind::ImmatureSignature => "Token is not valid yet",
+            ErrorKind::InvalidSignature => "Token signature is invalid",
+            ErrorKind::InvalidToken
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_)
+            | ErrorKind::InvalidClaimFormat(_) => "Token is malformed",
+            ErrorKind::InvalidIssuer
+            | ErrorKind::InvalidAudience
+            | ErrorKind::InvalidSubject
+            | ErrorKind::InvalidAlgorithm
+            | ErrorKind::MissingRequiredClaim(_) => "Token is invalid",
+            _ => return Self::server_error(&err).caused_by(err),
+        };
+
+        Self::unauthorized_with(message)
+            .with_header(
+                WWW_AUTHENTICATE,
+                HeaderValue::from_static(r#"Bearer error="invalid_token""#),
+            )
+            .caused_by(err)
+    }
+}
+
+/// A URL which doesn't parse came from the client, so it's a 400.
+#[cfg(feature = "url")]
+impl From<url::ParseError> for AppError {
+    #[track_caller]
+    fn from(err: url::ParseError) -> Self {
+        Self::bad_request(err).caused_by(err)
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for AppError {
+    #[track_caller]
+    fn from(err: anyhow::Error) -> Self {
+        Self::new(err.to_string()).caused_by(err)
+    }
+}
+
+/// A 422 with the "Unprocessable Entity" message and an error per field, keeping the full
+/// `ValidationErrors` as the source for logs. Errors of nested structs and lists are included
+/// under their path, like `address.city` or `items[0].name`.
+#[cfg(feature = "validator")]
+impl From<validator::ValidationErrors> for AppError {
+    #[track_caller]
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        flatten_validation_errors("", &errors, &mut fields);
+
+        fields.into_iter().fold(
+            Self::status(StatusCode::UNPROCESSABLE_ENTITY)
+                .json()
+                .caused_by(errors),
+            |err, (field, message)| err.with_field_error(field, message),
+        )
+    }
+}
+
+#[cfg(feature = "validator")]
+fn flatten_validation_errors(
+    prefix: &str,
+    errors: &validator::ValidationErrors,
+    fields: &mut Vec<(String, String)>,
+) {
+    use validator::ValidationErrorsKind;
+
+    let mut kinds: Vec<_> = errors.errors().iter().collect();
+    kinds.sort_by_key(|(field, _)| *field);
+
+    for (field, kind) in kinds {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                for field_error in field_errors {
+                    let message = match &field_error.message {
+                        Some(message) => message.to_string(),
+                        None => field_error.code.to_string(),
+                    };
+
+                    fields.push((path.clone(), message));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => {
+                flatten_validation_errors(&path, nested, fields);
+            }
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_validation_errors(&format!("{path}[{index}]"), nested, fields);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tonic")]
+impl From<AppError> for tonic::Status {
+    fn from(err: AppError) -> Self {
+        use axum::http::StatusCode;
+        use tonic::Code;
+
+        let code = match err.code {
+            StatusCode::BAD_REQUEST => Code::InvalidArgument,
+            StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+            StatusCode::FORBIDDEN => Code::PermissionDenied,
+            StatusCode::NOT_FOUND => Code::NotFound,
+            StatusCode::CONFLICT => Code::AlreadyExists,
+            StatusCode::PRECONDITION_FAILED => Code::FailedPrecondition,
+            StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+            StatusCode::NOT_IMPLEMENTED => Code::Unimplemented,
+            StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+            StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+            code if code.is_client_error() => Code::FailedPrecondition,
+            code if code.is_server_error() => Code::Internal,
+            _ => Code::Unknown,
+        };
+
+        tonic::Status::new(code, err.public_message().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppResult;
+
+    fn parse(s: &str) -> AppResult<i32> {
+        Ok(s.parse::<i32>()?)
+    }
+
+    #[tokio::test]
+    async fn test_json_rejection() {
+        use axum::body::Body;
+        use axum::extract::{FromRequest, Request};
+        use axum::http::header::CONTENT_TYPE;
+        use axum::Json;
+
+        let req = Request::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"[1, "two"]"#))
+            .unwrap();
+        let rejection = Json::<Vec<u32>>::from_request(req, &()).await.unwrap_err();
+        let err = AppError::from(rejection);
+
+        assert_eq!(err.code, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(err
+            .message
+            .starts_with("Failed to deserialize the JSON body"));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_utf8() {
+        fn decode(bytes: Vec<u8>) -> AppResult<String> {
+            Ok(String::from_utf8(bytes)?)
+        }
+
+        assert_eq!(decode(b"hi".to_vec()).unwrap(), "hi");
+
+        let err = decode(vec![0xff]).unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert!(err.source().is_some());
+
+        let err: AppError = String::from_utf8(vec![0xc3])
+            .unwrap_err()
+            .utf8_error()
+            .into();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_tuple() {
+        let err: AppError = (StatusCode::CONFLICT, "taken").into();
+        assert_eq!(err, AppError::code(StatusCode::CONFLICT)("taken"));
+
+        let err: AppError = (StatusCode::GONE, "user 1 deleted".to_string()).into();
+        assert_eq!(err.code, StatusCode::GONE);
+        assert_eq!(err.message, "user 1 deleted");
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        use axum::http::{HeaderName, HeaderValue};
+
+        fn link(url: &str) -> AppResult<AppError> {
+            Ok(AppError::not_found().with_header(
+                HeaderName::from_bytes(b"link")?,
+                HeaderValue::from_str(url)?,
+            ))
+        }
+
+        let err = link("<https://example.com>").unwrap();
+        assert_eq!(err.headers().unwrap()["link"], "<https://example.com>");
+
+        let err = link("bad\nvalue").unwrap_err();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(err.source().is_some());
+
+        let err: AppError = HeaderName::from_bytes(b"bad name").unwrap_err().into();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_to_app_error() {
+        enum DomainError {
+            Missing,
+            Broken(&'static str),
+        }
+
+        impl ToAppError for DomainError {
+            fn to_app_error(self) -> AppError {
+                match self {
+                    DomainError::Missing => AppError::not_found(),
+                    DomainError::Broken(why) => AppError::new(why),
+                }
+            }
+        }
+
+        fn handler(broken: bool) -> AppResult<()> {
+            if broken {
+                Err(DomainError::Broken("gears"))?;
+            }
+
+            Err(DomainError::Missing)?
+        }
+
+        assert_eq!(handler(false).unwrap_err(), AppError::not_found());
+        assert_eq!(handler(true).unwrap_err(), AppError::new("gears"));
+    }
+
+    #[test]
+    fn test_status_code() {
+        fn handler() -> AppResult<()> {
+            Err(StatusCode::FORBIDDEN.into())
+        }
+
+        assert_eq!(handler().unwrap_err(), AppError::forbidden());
+
+        let err: AppError = StatusCode::from_u16(599).unwrap().into();
+        assert_eq!(err.message, "");
+    }
+
+    #[test]
+    fn test_boxed() {
+        fn helper() -> Result<(), Box<dyn Error + Send + Sync>> {
+            Err("helper failed".into())
+        }
+
+        fn handler() -> AppResult<()> {
+            Ok(helper()?)
+        }
+
+        let err = handler().unwrap_err();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "helper failed");
+        assert_eq!(err.source().unwrap().to_string(), "helper failed");
+    }
+
+    #[test]
+    fn test_parse_int() {
+        let err = parse("nope").unwrap_err();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "invalid digit found in string");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_io() {
+        let err: AppError = io::Error::new(io::ErrorKind::NotFound, "gone").into();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "gone");
+    }
+
+    #[test]
+    fn test_serde_json() {
+        let err: AppError = serde_json::from_str::<i32>("{").unwrap_err().into();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_setup_error() {
+        let io = io::Error::new(io::ErrorKind::NotFound, "config.toml");
+        let err: AppError = SetupError::with_source("missing config", io).into();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "missing config");
+
+        let setup = err.source().unwrap();
+        assert_eq!(setup.source().unwrap().to_string(), "config.toml");
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_anyhow() {
+        use anyhow::Context;
+
+        fn load() -> anyhow::Result<()> {
+            Err(io::Error::new(io::ErrorKind::NotFound, "gone")).context("loading")
+        }
+
+        fn handler() -> AppResult<()> {
+            Ok(load()?)
+        }
+
+        let err = handler().unwrap_err();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.message, "loading");
+
+        let source = err.source().unwrap();
+        assert_eq!(source.source().unwrap().to_string(), "gone");
+    }
+
+    #[cfg(feature = "validator")]
+    #[test]
+    fn test_validator() {
+        use validator::{ValidationError, ValidationErrors};
+
+        let mut errors = ValidationErrors::new();
+        errors.add("email", ValidationError::new("email"));
+        errors.add(
+            "name",
+            ValidationError::new("length").with_message("too short".into()),
+        );
+
+        let mut address = ValidationErrors::new();
+        address.add("city", ValidationError::new("required"));
+        errors.merge_self("address", Err(address));
+
+        let err: AppError = errors.into();
+        assert_eq!(err.code, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(err.message, "Unprocessable Entity");
+        assert!(err.source().is_some());
+        assert_eq!(
+            err.field_errors(),
+            [
+                ("address.city".to_string(), "required".to_string()),
+                ("email".to_string(), "email".to_string()),
+                ("name".to_string(), "too short".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "tonic")]
+    #[test]
+    fn test_tonic() {
+        let status: tonic::Status = AppError::not_found().into();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert_eq!(status.message(), "Not Found");
+
+        let status: tonic::Status = AppError::new("boom").into();
+        assert_eq!(status.code(), tonic::Code::Internal);
+
+        let status: tonic::Status = AppError::code(StatusCode::IM_A_TEAPOT)("tea").into();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[test]
+    fn test_sqlx() {
+        let err: AppError = sqlx::Error::RowNotFound.into();
+        assert_eq!(err, AppError::not_found());
+
+        let err: AppError = sqlx::Error::PoolTimedOut.into();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_reqwest() {
+        let upstream = axum::http::Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body("")
+            .unwrap();
+        let err = reqwest::Response::from(upstream)
+            .error_for_status()
+            .unwrap_err();
+        let err: AppError = err.into();
+
+        assert_eq!(err.code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(err.source().is_some());
+
+        let err = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let err: AppError = err.into();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_join_error() {
+        let err = tokio::spawn(async { panic!("oh no") }).await.unwrap_err();
+        let err: AppError = err.into();
+
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(err.source().is_some());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_elapsed() {
+        let err = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let err: AppError = err.into();
+
+        assert_eq!(err.code, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(err.message, "request timed out");
+    }
+
+    #[cfg(feature = "jwt")]
+    #[test]
+    fn test_jwt() {
+        use axum::http::header::WWW_AUTHENTICATE;
+        use jsonwebtoken::errors::ErrorKind;
+
+        let err: AppError = jsonwebtoken::errors::Error::from(ErrorKind::ExpiredSignature).into();
+        assert_eq!(err.code, StatusCode::UNAUTHORIZED);
+        assert_eq!(err.message, "Token has expired");
+        assert_eq!(
+            err.headers().unwrap()[WWW_AUTHENTICATE],
+            r#"Bearer error="invalid_token""#
+        );
+
+        let err: AppError = jsonwebtoken::errors::Error::from(ErrorKind::InvalidKeyFormat).into();
+        assert_eq!(err.code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url() {
+        fn parse(input: &str) -> AppResult<url::Url> {
+            Ok(url::Url::parse(input)?)
+        }
+
+        assert!(parse("https://example.com").is_ok());
+
+        let err = parse("not a url").unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "relative URL without a base");
+    }
+}
@@ -0,0 +1,96 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::AppError;
+
+/// Like axum's `Json`, but a body which can't be parsed is rejected with a 400 `AppError` carrying
+/// the parse message, rendered as JSON. Other rejections, like a missing `Content-Type`, keep
+/// axum's status.
+///
+/// ```
+/// use whynot_errors::{json_ok, AppJson, JsonResult};
+///
+/// async fn create(AppJson(ids): AppJson<Vec<u32>>) -> JsonResult<usize> {
+///     json_ok(ids.len())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AppJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for AppJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(AppJson(value)),
+            Err(rejection) => {
+                let malformed = matches!(
+                    rejection,
+                    JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_)
+                );
+                let err = AppError::from(rejection).json();
+
+                Err(if malformed {
+                    err.with_code(StatusCode::BAD_REQUEST)
+                } else {
+                    err
+                })
+            }
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for AppJson<T> {
+    fn into_response(self) -> Response {
+        Json(self.0).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::CONTENT_TYPE;
+
+    async fn extract(body: &'static str) -> Result<AppJson<Vec<u32>>, AppError> {
+        let req = Request::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+
+        AppJson::from_request(req, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_app_json() {
+        assert_eq!(extract("[1, 2]").await.unwrap().0, vec![1, 2]);
+
+        let err = extract(r#"[1, "two"]"#).await.unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert!(err
+            .message
+            .starts_with("Failed to deserialize the JSON body"));
+
+        let response = err.into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_app_json_keeps_status() {
+        let req = Request::builder().body(Body::from("[1]")).unwrap();
+        let err = AppJson::<Vec<u32>>::from_request(req, &())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -0,0 +1,64 @@
+use axum::http::StatusCode;
+
+use crate::AppError;
+
+/// Collects per-field validation messages, for forms checked without the `validator` crate.
+/// Turns into a 422 with a JSON body like `{"errors": {"email": ["is required"]}}`.
+#[derive(Debug, Clone, Default)]
+pub struct FieldErrors {
+    errors: Vec<(String, String)>,
+}
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl ToString, msg: impl ToString) {
+        self.errors.push((field.to_string(), msg.to_string()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    #[track_caller]
+    pub fn into_app_error(self) -> AppError {
+        self.errors.into_iter().fold(
+            AppError::status(StatusCode::UNPROCESSABLE_ENTITY).json(),
+            |err, (field, msg)| err.with_field_error(field, msg),
+        )
+    }
+}
+
+impl From<FieldErrors> for AppError {
+    #[track_caller]
+    fn from(errors: FieldErrors) -> Self {
+        errors.into_app_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_errors() {
+        let mut errors = FieldErrors::new();
+        assert!(errors.is_empty());
+
+        errors.add("email", "is required");
+        errors.add("age", "must be a number");
+        errors.add("email", "must contain an @");
+
+        let err = errors.into_app_error();
+        assert_eq!(err.code, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            serde_json::to_value(err.error_body()).unwrap()["errors"],
+            serde_json::json!({
+                "email": ["is required", "must contain an @"],
+                "age": ["must be a number"],
+            })
+        );
+    }
+}
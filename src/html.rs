@@ -0,0 +1,95 @@
+use axum::response::{Html, IntoResponse, Response};
+
+use crate::config::html_error_renderer;
+use crate::AppError;
+
+impl AppError {
+    /// Convert into an HTML error page. Uses the renderer set for this code with
+    /// `set_html_error_renderer_for`, then the one for its class from
+    /// `set_html_error_renderer_for_severity`, then the one from `set_html_error_renderer`, if any.
+    pub fn into_html_response(self) -> Response {
+        self.respond_with(Self::html_response)
+    }
+
+    pub(crate) fn html_response(&self) -> Response {
+        let page = match html_error_renderer(self.code) {
+            Some(render) => render(self),
+            None => default_page(self),
+        };
+
+        (self.code, self.response_headers(), Html(page)).into_response()
+    }
+}
+
+fn default_page(err: &AppError) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{code}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 4rem auto; max-width: 40rem; color: #333; }}
+h1 {{ font-size: 2rem; margin-bottom: 0.5rem; }}
+p {{ color: #666; }}
+</style>
+</head>
+<body>
+<h1>{code}</h1>
+<p>{message}</p>
+</body>
+</html>
+"#,
+        code = err.code,
+        message = escape_html(&err.public_message()),
+    )
+}
+
+/// Escape text for use inside HTML, for custom renderers.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<b>\"Tom\" & 'Jerry'</b>"),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_default_page() {
+        let page = default_page(&AppError::bad_request("<script>"));
+
+        assert!(page.contains("<h1>400 Bad Request</h1>"));
+        assert!(page.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_html_response() {
+        let res = AppError::not_found().into_html_response();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+    }
+}
@@ -1,5 +1,49 @@
+mod macros;
+
 mod app_error;
+mod app_errors;
+mod builder;
+mod config;
+mod convert;
+mod extract;
+mod field_errors;
+mod html;
+#[cfg(feature = "tracing")]
+mod log;
+pub mod middleware;
+#[cfg(feature = "otel")]
+mod otel;
+pub mod prelude;
+mod problem;
+mod result_ext;
+#[cfg(feature = "sentry")]
+mod sentry;
+#[cfg(feature = "serde")]
+mod serialize;
 mod setup_error;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "tokio")]
+mod timeout;
+mod trailers;
+#[cfg(feature = "ws")]
+mod ws;
+#[cfg(feature = "xml")]
+mod xml;
 
 pub use app_error::*;
+pub use app_errors::*;
+pub use builder::*;
+pub use config::*;
+pub use convert::*;
+pub use extract::*;
+pub use field_errors::*;
+pub use html::*;
+pub use problem::*;
+pub use result_ext::*;
 pub use setup_error::*;
+#[cfg(feature = "tokio")]
+pub use timeout::*;
+pub use trailers::*;
+#[cfg(feature = "xml")]
+pub use xml::*;
@@ -0,0 +1,42 @@
+use tracing::{error, warn};
+
+use crate::app_error::Meta;
+use crate::config::log_responses;
+use crate::{AppError, Severity};
+
+impl AppError {
+    /// Log 5xx errors at `error` and 4xx errors at `warn`, unless disabled with
+    /// `set_log_responses`.
+    pub(crate) fn log(&self) {
+        if !log_responses() {
+            return;
+        }
+
+        match self.severity() {
+            Severity::ServerError => error!(
+                status = self.code.as_u16(),
+                message = %self.display_chain(),
+                debug = self.debug_message(),
+                location = self.location().map(display),
+                fields = self.meta().map(display),
+                backtrace = self.backtrace().map(display),
+                "Server error"
+            ),
+            Severity::ClientError => warn!(
+                status = self.code.as_u16(),
+                message = %self.display_chain(),
+                debug = self.debug_message(),
+                location = self.location().map(display),
+                fields = self.meta().map(display),
+                "Client error"
+            ),
+            Severity::Info => {}
+        }
+    }
+
+    /// The fields added with `with_field`, logged as one JSON object so they stay machine-readable.
+    fn meta(&self) -> Option<Meta<'_>> {
+        let fields = self.fields();
+        (!fields.is_empty()).then_some(Meta(fields))
+    }
+}
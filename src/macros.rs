@@ -0,0 +1,185 @@
+/// Build an `AppError` with a status code and a formatted message.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::app_error;
+///
+/// let id = 5;
+/// let err = app_error!(StatusCode::BAD_REQUEST, "bad id {id}");
+/// assert_eq!(err.message, "bad id 5");
+/// ```
+#[macro_export]
+macro_rules! app_error {
+    ($code:expr, $($arg:tt)+) => {
+        $crate::AppError::code($code)(::std::format!($($arg)+))
+    };
+}
+
+/// Return early with an `AppError` built by `app_error!`.
+#[macro_export]
+macro_rules! bail {
+    ($code:expr, $($arg:tt)+) => {
+        return ::std::result::Result::Err($crate::app_error!($code, $($arg)+))
+    };
+}
+
+/// Return early with an `AppError` built by `app_error!` if the condition is false.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::{ensure, AppResult};
+///
+/// fn check(name: &str) -> AppResult<()> {
+///     ensure!(!name.is_empty(), StatusCode::BAD_REQUEST, "name is required");
+///     Ok(())
+/// }
+///
+/// assert!(check("").is_err());
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $code:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::bail!($code, $($arg)+);
+        }
+    };
+}
+
+/// Unwrap a `Result` whose error is `ToString`, or return early with an `AppError` with the given
+/// code and the error's message. Like `?`, with the status picked inline.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::{try_app, AppResult};
+///
+/// fn parse(s: &str) -> AppResult<i32> {
+///     let n = try_app!(s.parse::<i32>(), StatusCode::BAD_REQUEST);
+///     Ok(n * 2)
+/// }
+///
+/// assert_eq!(parse("x").unwrap_err().code, StatusCode::BAD_REQUEST);
+/// ```
+#[macro_export]
+macro_rules! try_app {
+    ($result:expr, $code:expr $(,)?) => {
+        match $result {
+            ::std::result::Result::Ok(value) => value,
+            ::std::result::Result::Err(err) => {
+                return ::std::result::Result::Err($crate::AppError::code($code)(err));
+            }
+        }
+    };
+}
+
+/// Implement `ToAppError` for a domain error by mapping each pattern to a status code and a
+/// formatted message, which can use the bindings of the pattern.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use whynot_errors::{impl_to_app_error, AppError};
+///
+/// enum UserError {
+///     Missing(u32),
+///     Banned,
+/// }
+///
+/// impl_to_app_error!(UserError {
+///     UserError::Missing(id) => (StatusCode::NOT_FOUND, "user {id} not found"),
+///     UserError::Banned => (StatusCode::FORBIDDEN, "user is banned"),
+/// });
+///
+/// let err: AppError = UserError::Missing(4).into();
+/// assert_eq!(err.message, "user 4 not found");
+/// ```
+#[macro_export]
+macro_rules! impl_to_app_error {
+    ($ty:ty { $($pat:pat => ($code:expr, $($arg:tt)+)),+ $(,)? }) => {
+        impl $crate::ToAppError for $ty {
+            fn to_app_error(self) -> $crate::AppError {
+                match self {
+                    $($pat => $crate::app_error!($code, $($arg)+),)+
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AppResult;
+    use axum::http::StatusCode;
+
+    fn check(id: i32) -> AppResult<i32> {
+        if id < 0 {
+            bail!(StatusCode::BAD_REQUEST, "bad id {}", id);
+        }
+
+        Ok(id)
+    }
+
+    fn positive(n: i32) -> AppResult<i32> {
+        ensure!(n > 0, StatusCode::BAD_REQUEST, "{} is not positive", n);
+        Ok(n)
+    }
+
+    #[test]
+    fn test_app_error() {
+        let id = 7;
+        let err = app_error!(StatusCode::NOT_FOUND, "user {id} not found");
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "user 7 not found");
+    }
+
+    #[test]
+    fn test_bail() {
+        assert_eq!(check(1).unwrap(), 1);
+
+        let err = check(-1).unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "bad id -1");
+    }
+
+    #[test]
+    fn test_impl_to_app_error() {
+        enum OrderError {
+            Missing(u32),
+            Closed,
+        }
+
+        impl_to_app_error!(OrderError {
+            OrderError::Missing(id) => (StatusCode::NOT_FOUND, "order {id} not found"),
+            OrderError::Closed => (StatusCode::CONFLICT, "order is closed"),
+        });
+
+        let err: crate::AppError = OrderError::Missing(3).into();
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "order 3 not found");
+
+        let err: crate::AppError = OrderError::Closed.into();
+        assert_eq!(err.code, StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_try_app() {
+        fn double(s: &str) -> AppResult<i32> {
+            let n = try_app!(s.parse::<i32>(), StatusCode::BAD_REQUEST);
+            Ok(n * 2)
+        }
+
+        assert_eq!(double("4").unwrap(), 8);
+
+        let err = double("four").unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "invalid digit found in string");
+    }
+
+    #[test]
+    fn test_ensure() {
+        assert_eq!(positive(3).unwrap(), 3);
+
+        let err = positive(0).unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "0 is not positive");
+    }
+}
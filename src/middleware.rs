@@ -0,0 +1,797 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::body::{Body, HttpBody};
+use axum::extract::Request;
+use axum::http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::app_error::RenderedError;
+use crate::problem::PROBLEM_JSON;
+use crate::AppError;
+
+/// The header carrying the id of a request.
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The body formats an `AppError` can be negotiated into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Problem,
+    Html,
+}
+
+/// Middleware which re-renders `AppError` responses based on the request's `Accept` header.
+/// `application/json` gets a JSON body, `application/problem+json` problem details and `text/html`
+/// an HTML page, anything else is left alone.
+///
+/// ```no_run
+/// use axum::{middleware, routing::get, Router};
+/// use whynot_errors::{middleware::negotiate, AppError};
+///
+/// async fn handler() -> Result<(), AppError> {
+///     Err(AppError::new("nope"))
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/", get(handler))
+///     .layer(middleware::from_fn(negotiate));
+/// ```
+pub async fn negotiate(req: Request, next: Next) -> Response {
+    let format = accepted_format(req.headers());
+
+    let response = next.run(req).await;
+
+    match format {
+        Some(format) => rerender(response, format),
+        None => response,
+    }
+}
+
+/// The known format with the highest `q` value, the earliest one on a tie. `q=0` means not acceptable.
+fn preferred_format(accept: &str) -> Option<Format> {
+    let mut best: Option<(Format, f32)> = None;
+
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let format = match params.next().unwrap_or_default().trim() {
+            "application/json" => Format::Json,
+            "application/problem+json" => Format::Problem,
+            "text/html" => Format::Html,
+            _ => continue,
+        };
+
+        let q = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((format, q));
+        }
+    }
+
+    best.map(|(format, _)| format)
+}
+
+fn rerender(response: Response, format: Format) -> Response {
+    let rendered = match response.extensions().get::<RenderedError>() {
+        Some(RenderedError(err)) => err.formatted_response(format),
+        None => return response,
+    };
+
+    replace_body(response, rendered)
+}
+
+fn accepted_format(headers: &HeaderMap) -> Option<Format> {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .and_then(preferred_format)
+}
+
+impl AppError {
+    /// Convert into a response for `req`, negotiating the body format from its `Accept` header like
+    /// `negotiate` and setting the `instance` to the request path, unless one was set already.
+    /// The request method is added as a `method` field, and `HEAD` requests get an empty body.
+    pub fn into_response_with_request(self, req: &Parts) -> Response {
+        let format = accepted_format(&req.headers);
+        let err = self.with_request(&req.method, &req.uri);
+
+        if req.method == Method::HEAD {
+            return err.into_head_response();
+        }
+
+        match format {
+            Some(format) => err.respond_with(|err| err.formatted_response(format)),
+            None => err.into_response(),
+        }
+    }
+
+    fn with_request(mut self, method: &Method, uri: &Uri) -> Self {
+        if self.extras().is_none_or(|extras| extras.instance.is_none()) {
+            self = self.with_instance(uri.path());
+        }
+
+        self.with_field("method", method)
+    }
+
+    fn formatted_response(&self, format: Format) -> Response {
+        match format {
+            Format::Json => self.json_response(),
+            Format::Problem => self.problem_response(),
+            Format::Html => self.html_response(),
+        }
+    }
+}
+
+/// Middleware which does `AppError::into_response_with_request` for every `AppError` response, so
+/// error bodies are negotiated from the `Accept` header and carry the request path as their `instance`.
+/// Errors for `HEAD` requests keep their status and headers but lose the body.
+///
+/// ```no_run
+/// use axum::{middleware, routing::get, Router};
+/// use whynot_errors::{middleware::request_context, AppError};
+///
+/// async fn handler() -> Result<(), AppError> {
+///     Err(AppError::not_found().json())
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/users/{id}", get(handler))
+///     .layer(middleware::from_fn(request_context));
+/// ```
+pub async fn request_context(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+    let format = accepted_format(req.headers());
+
+    let mut response = next.run(req).await;
+
+    let Some(RenderedError(err)) = response.extensions_mut().remove::<RenderedError>() else {
+        return response;
+    };
+
+    // Already logged when the handler's response was made, so only render it again.
+    let err = err.with_request(&method, &uri);
+    let rendered = match format {
+        Some(format) => err.formatted_response(format),
+        None => err.clone().into_rendered_response(),
+    };
+
+    let mut response = replace_body(response, rendered);
+    response.extensions_mut().insert(RenderedError(err));
+
+    if method == Method::HEAD {
+        let (parts, _) = response.into_parts();
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    response
+}
+
+/// Swap the body of `response` for the one in `rendered`, keeping any other headers the handler set.
+fn replace_body(response: Response, rendered: Response) -> Response {
+    let (mut parts, _) = response.into_parts();
+    let (rendered_parts, body) = rendered.into_parts();
+
+    for header in [CONTENT_TYPE, CONTENT_LENGTH] {
+        match rendered_parts.headers.get(&header) {
+            Some(value) => parts.headers.insert(header, value.clone()),
+            None => parts.headers.remove(header),
+        };
+    }
+
+    Response::from_parts(parts, body)
+}
+
+/// The id of the current request, put in the request extensions by `request_id`.
+/// Handlers can get it with `Extension<RequestId>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Middleware which makes sure every request has an id, taken from the `X-Request-Id` header or
+/// generated. The id is echoed in the `X-Request-Id` response header and added to `AppError` JSON
+/// and problem details bodies. With the `tracing` feature, the request runs in a span carrying the
+/// id, so it's in the logs too, and with the `sentry` feature it's a `request_id` tag on the events
+/// captured during the request.
+///
+/// ```no_run
+/// use axum::{middleware, routing::get, Router};
+/// use whynot_errors::middleware::request_id;
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { "hi" }))
+///     .layer(middleware::from_fn(request_id));
+/// ```
+pub async fn request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(generate_request_id);
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let response = next.run(req);
+
+    // Errors are captured before `with_request_id` adds the id to them, so tag the scope instead.
+    #[cfg(feature = "sentry")]
+    let response = {
+        use sentry_core::{Hub, SentryFutureExt};
+
+        let hub = Hub::new_from_top(Hub::current());
+        hub.configure_scope(|scope| scope.set_tag("request_id", &id));
+        response.bind_hub(hub)
+    };
+
+    #[cfg(feature = "tracing")]
+    let response = {
+        use tracing::Instrument;
+
+        response
+            .instrument(tracing::info_span!("request", request_id = %id))
+            .await
+    };
+    #[cfg(not(feature = "tracing"))]
+    let response = response.await;
+
+    with_request_id(response, id)
+}
+
+fn generate_request_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+
+    format!(
+        "{nanos:016x}{:08x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn with_request_id(mut response: Response, id: String) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID, value);
+    }
+
+    let Some(RenderedError(err)) = response.extensions_mut().remove::<RenderedError>() else {
+        return response;
+    };
+
+    let err = err.with_request_id(id);
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok());
+
+    // Only the structured bodies carry the id, plain text and HTML just get the header.
+    let rendered = match content_type {
+        Some("application/json") => Some(err.json_response()),
+        Some(PROBLEM_JSON) => Some(err.problem_response()),
+        #[cfg(feature = "xml")]
+        Some(crate::PROBLEM_XML) => Some(err.problem_xml_response()),
+        _ => None,
+    };
+
+    let mut response = match rendered {
+        Some(rendered) => replace_body(response, rendered),
+        None => response,
+    };
+
+    response.extensions_mut().insert(RenderedError(err));
+    response
+}
+
+/// Layer which turns panics in the inner service into `AppError::server_error` responses.
+/// The panic message is used as the error message, so turn on `set_redact_server_errors` to keep it
+/// from clients.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use whynot_errors::middleware::CatchPanicLayer;
+///
+/// async fn handler() -> &'static str {
+///     panic!("oh no")
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/", get(handler))
+///     .layer(CatchPanicLayer);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CatchPanicLayer;
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic { inner }
+    }
+}
+
+/// Service created by `CatchPanicLayer`.
+#[derive(Debug, Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+impl<S, B> Service<axum::http::Request<B>> for CatchPanic<S>
+where
+    S: Service<axum::http::Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: axum::http::Request<B>) -> Self::Future {
+        let future = match panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(req))) {
+            Ok(future) => future,
+            Err(panic) => return Box::pin(async move { Ok(panic_response(panic)) }),
+        };
+
+        Box::pin(async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(result) => result,
+                Err(panic) => Ok(panic_response(panic)),
+            }
+        })
+    }
+}
+
+fn panic_response(panic: Box<dyn Any + Send>) -> Response {
+    let message = if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Handler panicked".to_string()
+    };
+
+    AppError::server_error(message).into_response()
+}
+
+/// Layer which gives bodiless 4xx and 5xx responses, like a bare `StatusCode` from a handler or
+/// an extractor, the same body an `AppError` would have. The canonical reason is used as the message
+/// unless another one is set with `with_message`, and any headers already set are kept. Plain text
+/// by default, call `json` for JSON bodies.
+///
+/// ```no_run
+/// use axum::{http::StatusCode, routing::get, Router};
+/// use whynot_errors::middleware::ErrorBodyLayer;
+///
+/// let app: Router = Router::new()
+///     .route("/", get(|| async { StatusCode::FORBIDDEN }))
+///     .layer(ErrorBodyLayer::new().json());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ErrorBodyLayer {
+    json: bool,
+    gateway_only: bool,
+    messages: Arc<Vec<(StatusCode, Cow<'static, str>)>>,
+}
+
+impl ErrorBodyLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only fill in bodiless 502, 503 and 504 responses, with messages describing the gateway
+    /// failure. For services acting as a reverse proxy.
+    pub fn gateway() -> Self {
+        Self {
+            gateway_only: true,
+            ..Self::default()
+        }
+        .with_message(
+            StatusCode::BAD_GATEWAY,
+            "The upstream service returned an invalid response or could not be reached",
+        )
+        .with_message(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The upstream service is unavailable",
+        )
+        .with_message(
+            StatusCode::GATEWAY_TIMEOUT,
+            "The upstream service did not respond in time",
+        )
+    }
+
+    /// Render the filled in bodies as JSON.
+    pub fn json(mut self) -> Self {
+        self.json = true;
+        self
+    }
+
+    /// Use `message` instead of the canonical reason for bodiless responses with `code`.
+    pub fn with_message(mut self, code: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+        let messages = Arc::make_mut(&mut self.messages);
+        messages.retain(|(existing, _)| *existing != code);
+        messages.push((code, message.into()));
+        self
+    }
+
+    fn applies_to(&self, status: StatusCode) -> bool {
+        if self.gateway_only {
+            matches!(
+                status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            )
+        } else {
+            status.is_client_error() || status.is_server_error()
+        }
+    }
+
+    fn message(&self, status: StatusCode) -> Cow<'static, str> {
+        self.messages
+            .iter()
+            .find(|(code, _)| *code == status)
+            .map(|(_, message)| message.clone())
+            .unwrap_or(Cow::Borrowed(status.canonical_reason().unwrap_or_default()))
+    }
+}
+
+impl<S> Layer<S> for ErrorBodyLayer {
+    type Service = ErrorBody<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorBody {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service created by `ErrorBodyLayer`.
+#[derive(Debug, Clone)]
+pub struct ErrorBody<S> {
+    inner: S,
+    layer: ErrorBodyLayer,
+}
+
+impl<S, B> Service<axum::http::Request<B>> for ErrorBody<S>
+where
+    S: Service<axum::http::Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: axum::http::Request<B>) -> Self::Future {
+        let future = self.inner.call(req);
+        let layer = self.layer.clone();
+
+        Box::pin(async move { Ok(fill_error_body(future.await?, &layer)) })
+    }
+}
+
+fn fill_error_body(response: Response, layer: &ErrorBodyLayer) -> Response {
+    let status = response.status();
+    let is_empty = response.body().size_hint().exact() == Some(0);
+
+    if !layer.applies_to(status)
+        || !is_empty
+        || response.extensions().get::<RenderedError>().is_some()
+    {
+        return response;
+    }
+
+    let mut err = AppError::raw(status, layer.message(status));
+    err.location = None;
+
+    let err = if layer.json { err.json() } else { err };
+    let mut response = replace_body(response, err.clone().into_response());
+    response.extensions_mut().insert(RenderedError(err));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        async fn handler() -> Result<(), AppError> {
+            Err(AppError::new("nope"))
+        }
+
+        Router::new()
+            .route("/", get(handler))
+            .layer(axum::middleware::from_fn(negotiate))
+    }
+
+    async fn content_type(accept: &str) -> String {
+        let req = Request::builder()
+            .uri("/")
+            .header(ACCEPT, accept)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app().oneshot(req).await.unwrap();
+        res.headers()[CONTENT_TYPE].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_preferred_format() {
+        assert_eq!(preferred_format("application/json"), Some(Format::Json));
+        assert_eq!(
+            preferred_format("text/html;q=0.9, application/json"),
+            Some(Format::Json)
+        );
+        assert_eq!(
+            preferred_format("text/html, application/json"),
+            Some(Format::Html)
+        );
+        assert_eq!(preferred_format("application/json;q=0"), None);
+        assert_eq!(
+            preferred_format("application/json; q=0.5, text/html;q=0.8"),
+            Some(Format::Html)
+        );
+        assert_eq!(
+            preferred_format("application/problem+json"),
+            Some(Format::Problem)
+        );
+        assert_eq!(preferred_format("*/*"), None);
+    }
+
+    #[tokio::test]
+    async fn test_negotiate() {
+        assert_eq!(content_type("application/json").await, "application/json");
+        assert_eq!(content_type("text/html").await, "text/html; charset=utf-8");
+        assert_eq!(content_type("*/*").await, "text/plain; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        async fn handler() -> Result<(), AppError> {
+            Err(AppError::not_found().json())
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(axum::middleware::from_fn(request_id));
+
+        let req = Request::builder()
+            .uri("/")
+            .header(X_REQUEST_ID, "abc123")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[X_REQUEST_ID], "abc123");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "abc123");
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert!(!res.headers()[X_REQUEST_ID].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_problem_details() {
+        async fn handler() -> Response {
+            AppError::not_found().into_problem_details()
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(axum::middleware::from_fn(request_id));
+
+        let req = Request::builder()
+            .uri("/")
+            .header(X_REQUEST_ID, "abc123")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[CONTENT_TYPE], PROBLEM_JSON);
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "abc123");
+    }
+
+    #[tokio::test]
+    async fn test_error_body() {
+        let app = Router::new()
+            .route("/forbidden", get(|| async { StatusCode::FORBIDDEN }))
+            .route(
+                "/teapot",
+                get(|| async { (StatusCode::IM_A_TEAPOT, "short and stout") }),
+            )
+            .layer(ErrorBodyLayer::new().json());
+
+        let req = Request::builder()
+            .uri("/forbidden")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Forbidden");
+
+        let req = Request::builder()
+            .uri("/teapot")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "short and stout");
+    }
+
+    #[tokio::test]
+    async fn test_gateway_error_body() {
+        let app = Router::new()
+            .route("/timeout", get(|| async { StatusCode::GATEWAY_TIMEOUT }))
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .layer(
+                ErrorBodyLayer::gateway()
+                    .with_message(StatusCode::GATEWAY_TIMEOUT, "billing took too long"),
+            );
+
+        let req = Request::builder()
+            .uri("/timeout")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "billing took too long");
+
+        let req = Request::builder()
+            .uri("/missing")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        async fn handler() -> &'static str {
+            panic!("oh no")
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(CatchPanicLayer);
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let err = &res.extensions().get::<RenderedError>().unwrap().0;
+        assert_eq!(err.message, "oh no");
+    }
+
+    #[tokio::test]
+    async fn test_into_response_with_request() {
+        let (parts, _) = Request::builder()
+            .method(Method::DELETE)
+            .uri("/users/1?force=true")
+            .header(ACCEPT, "application/json")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let res = AppError::not_found().into_response_with_request(&parts);
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["instance"], "/users/1");
+        assert_eq!(json["meta"]["method"], "DELETE");
+    }
+
+    #[tokio::test]
+    async fn test_request_context() {
+        async fn handler() -> Result<(), AppError> {
+            Err(AppError::conflict())
+        }
+
+        let app = Router::new()
+            .route("/users", get(handler))
+            .layer(axum::middleware::from_fn(request_context));
+
+        let req = Request::builder()
+            .uri("/users")
+            .header(ACCEPT, "application/problem+json")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/problem+json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["instance"], "/users");
+    }
+
+    #[tokio::test]
+    async fn test_request_context_head() {
+        async fn handler() -> Result<(), AppError> {
+            Err(AppError::not_found().json())
+        }
+
+        let app = Router::new()
+            .route("/", get(handler).head(handler))
+            .layer(axum::middleware::from_fn(request_context));
+
+        let req = Request::builder()
+            .method(Method::HEAD)
+            .uri("/")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+    }
+}
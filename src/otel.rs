@@ -0,0 +1,68 @@
+use opentelemetry::trace::{get_active_span, Status};
+use opentelemetry::KeyValue;
+
+use crate::AppError;
+
+impl AppError {
+    /// Record the error as an `exception` event on the active OpenTelemetry span, marking the
+    /// span as failed for 5xx errors.
+    pub(crate) fn record_exception(&self) {
+        get_active_span(|span| {
+            let mut attributes = vec![
+                KeyValue::new("exception.type", std::any::type_name::<AppError>()),
+                KeyValue::new("exception.message", self.display_chain()),
+                KeyValue::new("http.response.status_code", i64::from(self.code.as_u16())),
+            ];
+
+            if let Some(backtrace) = self.backtrace() {
+                attributes.push(KeyValue::new("exception.stacktrace", backtrace.to_string()));
+            }
+
+            span.add_event("exception", attributes);
+
+            if self.is_server_error() {
+                span.set_status(Status::error(self.message.clone()));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    fn record(err: AppError) -> opentelemetry_sdk::trace::SpanData {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        provider
+            .tracer("test")
+            .in_span("request", |_| err.record_exception());
+
+        exporter.get_finished_spans().unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_record_exception() {
+        let span = record(AppError::new("db down"));
+
+        let event = &span.events.events[0];
+        assert_eq!(event.name, "exception");
+        assert!(event
+            .attributes
+            .contains(&KeyValue::new("exception.message", "db down")));
+        assert_eq!(span.status, Status::error("db down"));
+    }
+
+    #[test]
+    fn test_record_client_error() {
+        let span = record(AppError::not_found());
+
+        assert_eq!(span.events.events[0].name, "exception");
+        assert_eq!(span.status, Status::Unset);
+    }
+}
@@ -0,0 +1,20 @@
+//! The items most handlers need.
+//!
+//! ```
+//! use whynot_errors::prelude::*;
+//!
+//! fn find(id: u32) -> JsonResult<u32> {
+//!     ensure!(id > 0, StatusCode::BAD_REQUEST, "ids start at 1");
+//!     json_ok(id)
+//! }
+//!
+//! assert_eq!(find(0).unwrap_err().code, StatusCode::BAD_REQUEST);
+//! ```
+
+pub use axum::http::StatusCode;
+
+pub use crate::{
+    app_error, bail, ensure, html_ok, html_status, impl_to_app_error, json_err, json_ok,
+    json_status, ok, try_app, try_collect_app, AppError, AppJson, AppResult, FlattenApp,
+    HtmlResult, IntoAppResult, JsonResult, OptionExt, ResultExt, ToAppError,
+};
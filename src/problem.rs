@@ -0,0 +1,220 @@
+use std::borrow::Cow;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+use crate::AppError;
+
+/// Content type for RFC 7807 bodies.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An RFC 7807 problem details body.
+#[derive(Serialize)]
+pub(crate) struct Problem<'a> {
+    #[serde(rename = "type")]
+    pub(crate) problem_type: &'a str,
+    pub(crate) title: &'a str,
+    pub(crate) status: u16,
+    pub(crate) detail: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) instance: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) request_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) errors: Vec<ProblemFieldError<'a>>,
+}
+
+/// An entry of the `errors` extension member, one per `with_field_error`.
+#[derive(Serialize)]
+pub(crate) struct ProblemFieldError<'a> {
+    pub(crate) field: &'a str,
+    pub(crate) message: &'a str,
+}
+
+impl AppError {
+    /// Set the problem `type` URI used by `into_problem_details`. Defaults to `about:blank`.
+    pub fn with_type(mut self, uri: impl ToString) -> Self {
+        self.extras_mut().problem_type = Some(uri.to_string());
+        self
+    }
+
+    /// Set the problem `instance` URI used by `into_problem_details`, also included in JSON bodies.
+    pub fn with_instance(mut self, uri: impl ToString) -> Self {
+        self.extras_mut().instance = Some(uri.to_string());
+        self
+    }
+
+    /// Convert into an `application/problem+json` response.
+    pub fn into_problem_details(self) -> Response {
+        self.respond_with(Self::problem_response)
+    }
+
+    pub(crate) fn problem_response(&self) -> Response {
+        (
+            self.code,
+            self.response_headers(),
+            [(CONTENT_TYPE, PROBLEM_JSON)],
+            Json(self.problem()),
+        )
+            .into_response()
+    }
+
+    pub(crate) fn problem(&self) -> Problem<'_> {
+        let extras = self.extras();
+
+        Problem {
+            problem_type: extras
+                .and_then(|extras| extras.problem_type.as_deref())
+                .unwrap_or("about:blank"),
+            title: self.code.canonical_reason().unwrap_or_default(),
+            status: self.code.as_u16(),
+            detail: self.public_message(),
+            instance: extras.and_then(|extras| extras.instance.as_deref()),
+            request_id: self.request_id(),
+            errors: self
+                .field_errors()
+                .iter()
+                .map(|(field, message)| ProblemFieldError { field, message })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "problemdetails")]
+impl From<AppError> for problemdetails::Problem {
+    fn from(err: AppError) -> Self {
+        let mut problem = problemdetails::new(err.code);
+
+        if let Ok(serde_json::Value::Object(body)) = serde_json::to_value(err.problem()) {
+            for (key, value) in body {
+                problem = problem.with_value(&key, value);
+            }
+        }
+
+        if let Some(id) = err.code_id() {
+            problem = problem.with_value("error_code", id);
+        }
+
+        if !err.fields().is_empty() {
+            let meta: serde_json::Map<_, _> = err
+                .fields()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone().into()))
+                .collect();
+
+            problem = problem.with_value("meta", meta);
+        }
+
+        problem
+    }
+}
+
+#[cfg(feature = "problemdetails")]
+impl From<problemdetails::Problem> for AppError {
+    #[track_caller]
+    fn from(problem: problemdetails::Problem) -> Self {
+        let text = |key: &str| problem.body.get(key).and_then(|value| value.as_str());
+
+        let message = text("detail")
+            .or_else(|| text("title"))
+            .or_else(|| problem.status_code.canonical_reason())
+            .unwrap_or_default();
+
+        let mut err = AppError::raw(problem.status_code, message.to_string());
+
+        if let Some(uri) = text("type").filter(|uri| *uri != "about:blank") {
+            err = err.with_type(uri);
+        }
+
+        if let Some(uri) = text("instance") {
+            err = err.with_instance(uri);
+        }
+
+        if let Some(id) = text("error_code") {
+            err = err.with_code_id(id);
+        }
+
+        if let Some(meta) = problem.body.get("meta").and_then(|meta| meta.as_object()) {
+            for (key, value) in meta {
+                let value = value
+                    .as_str()
+                    .map_or_else(|| value.to_string(), str::to_string);
+                err = err.with_field(key, value);
+            }
+        }
+
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_problem_body() {
+        let err = AppError::not_found()
+            .with_type("https://example.com/probs/missing")
+            .with_instance("/users/1");
+
+        assert_eq!(
+            serde_json::to_value(err.problem()).unwrap(),
+            serde_json::json!({
+                "type": "https://example.com/probs/missing",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "Not Found",
+                "instance": "/users/1",
+            })
+        );
+    }
+
+    #[test]
+    fn test_problem_field_errors() {
+        let err = AppError::unprocessable_entity("invalid signup")
+            .with_field_error("email", "is required")
+            .with_field_error("age", "must be a number");
+
+        assert_eq!(
+            serde_json::to_value(err.problem()).unwrap()["errors"],
+            serde_json::json!([
+                {"field": "email", "message": "is required"},
+                {"field": "age", "message": "must be a number"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_problem_response() {
+        let res = AppError::code(StatusCode::CONFLICT)("taken").into_problem_details();
+
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(res.headers()[CONTENT_TYPE], PROBLEM_JSON);
+    }
+
+    #[cfg(feature = "problemdetails")]
+    #[test]
+    fn test_problemdetails() {
+        let err = AppError::code(StatusCode::CONFLICT)("email taken")
+            .with_instance("/users")
+            .with_code_id("EMAIL_TAKEN")
+            .with_field("email", "a@b.c");
+
+        let problem: problemdetails::Problem = err.into();
+        assert_eq!(problem.status_code, StatusCode::CONFLICT);
+        assert_eq!(problem.body["detail"], "email taken");
+        assert_eq!(problem.body["error_code"], "EMAIL_TAKEN");
+        assert_eq!(problem.body["meta"]["email"], "a@b.c");
+
+        let back: AppError = problem.into();
+        assert_eq!(back.code, StatusCode::CONFLICT);
+        assert_eq!(back.message, "email taken");
+        assert_eq!(back.code_id(), Some("EMAIL_TAKEN"));
+        assert_eq!(back.fields(), [("email".to_string(), "a@b.c".to_string())]);
+        assert_eq!(back.problem().instance, Some("/users"));
+        assert_eq!(back.problem().problem_type, "about:blank");
+    }
+}
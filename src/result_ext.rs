@@ -0,0 +1,250 @@
+use axum::http::StatusCode;
+
+use crate::{AppError, AppResult};
+
+/// Extra combinators for `AppResult`.
+pub trait ResultExt<T> {
+    /// Add context to the error, see `AppError::context`.
+    fn context(self, msg: impl ToString) -> AppResult<T>;
+
+    /// Add lazily built context to the error. The closure only runs on the error path.
+    fn with_context<C: ToString>(self, f: impl FnOnce() -> C) -> AppResult<T>;
+
+    /// Replace the status code of the error, keeping the message.
+    fn or_status(self, code: StatusCode) -> AppResult<T>;
+
+    /// Rewrite the code of the error, whatever it was. An alias of `or_status` which reads better
+    /// at API boundaries.
+    fn map_to(self, code: StatusCode) -> AppResult<T>;
+
+    /// Turn any error into a 502, for handlers proxying an upstream service.
+    fn bad_gateway(self) -> AppResult<T>;
+
+    /// Turn any error into a 503.
+    fn service_unavailable(self) -> AppResult<T>;
+
+    /// Replace the message of the error, keeping the status code.
+    fn or_message(self, msg: impl ToString) -> AppResult<T>;
+
+    /// Log the error, if any, and pass the result through untouched.
+    /// Does nothing without the `tracing` feature, or when disabled with `set_log_responses`.
+    fn inspect_err_log(self) -> AppResult<T>;
+}
+
+impl<T> ResultExt<T> for AppResult<T> {
+    fn context(self, msg: impl ToString) -> AppResult<T> {
+        self.map_err(|err| err.context(msg))
+    }
+
+    fn with_context<C: ToString>(self, f: impl FnOnce() -> C) -> AppResult<T> {
+        self.map_err(|err| err.context(f()))
+    }
+
+    fn or_status(self, code: StatusCode) -> AppResult<T> {
+        self.map_err(|err| err.with_code(code))
+    }
+
+    fn map_to(self, code: StatusCode) -> AppResult<T> {
+        self.or_status(code)
+    }
+
+    fn bad_gateway(self) -> AppResult<T> {
+        self.map_to(StatusCode::BAD_GATEWAY)
+    }
+
+    fn service_unavailable(self) -> AppResult<T> {
+        self.map_to(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    fn or_message(self, msg: impl ToString) -> AppResult<T> {
+        self.map_err(|err| err.with_message(msg))
+    }
+
+    fn inspect_err_log(self) -> AppResult<T> {
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &self {
+            err.log();
+        }
+
+        self
+    }
+}
+
+/// Collapse an `AppResult<AppResult<T>>`, like the result of a spawned task, into an `AppResult<T>`.
+pub trait FlattenApp<T> {
+    fn flatten_app(self) -> AppResult<T>;
+}
+
+impl<T> FlattenApp<T> for AppResult<AppResult<T>> {
+    fn flatten_app(self) -> AppResult<T> {
+        self.and_then(|inner| inner)
+    }
+}
+
+/// Convert any `Result` with a `ToString` error into an `AppResult`.
+pub trait IntoAppResult<T> {
+    /// Convert the error into an `AppError` with the given code.
+    fn into_app(self, code: StatusCode) -> AppResult<T>;
+
+    /// Convert the error into a 500 `AppError`, see `AppError::server_error`. Unlike `AppError::new`
+    /// this ignores `set_default_error_code`.
+    fn into_app_500(self) -> AppResult<T>;
+}
+
+impl<T, E: ToString> IntoAppResult<T> for Result<T, E> {
+    #[track_caller]
+    fn into_app(self, code: StatusCode) -> AppResult<T> {
+        self.map_err(AppError::code(code))
+    }
+
+    #[track_caller]
+    fn into_app_500(self) -> AppResult<T> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(err) => Err(AppError::server_error(err)),
+        }
+    }
+}
+
+/// Turn an `Option` into an `AppResult`, for the look up a row and 404 pattern.
+pub trait OptionExt<T> {
+    /// `None` becomes `AppError::not_found`.
+    fn require(self) -> AppResult<T>;
+
+    /// `None` becomes an error with the given code and message.
+    fn require_or(self, code: StatusCode, msg: impl ToString) -> AppResult<T>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn require(self) -> AppResult<T> {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(AppError::not_found()),
+        }
+    }
+
+    #[track_caller]
+    fn require_or(self, code: StatusCode, msg: impl ToString) -> AppResult<T> {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(AppError::code(code)(msg)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context() {
+        let r: AppResult<()> = Err(AppError::not_found());
+        let err = r.context("user 1").unwrap_err();
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "user 1: Not Found");
+    }
+
+    #[test]
+    fn test_with_context_is_lazy() {
+        let ok: AppResult<i32> = Ok(1);
+        let r = ok.with_context(|| -> String { panic!("should not run") });
+
+        assert_eq!(r.unwrap(), 1);
+
+        let err: AppResult<i32> = Err(AppError::new("boom"));
+        let err = err.with_context(|| format!("step {}", 2)).unwrap_err();
+
+        assert_eq!(err.message, "step 2: boom");
+    }
+
+    #[test]
+    fn test_or_status() {
+        let r: AppResult<()> = Err(AppError::new("bad input"));
+        let err = r.or_status(StatusCode::BAD_REQUEST).unwrap_err();
+
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.message, "bad input");
+    }
+
+    #[test]
+    fn test_map_to() {
+        let r: AppResult<()> = Err(AppError::not_found());
+        let err = r.bad_gateway().unwrap_err();
+
+        assert_eq!(err.code, StatusCode::BAD_GATEWAY);
+        assert_eq!(err.message, "Not Found");
+
+        let r: AppResult<()> = Err(AppError::new("pool exhausted"));
+        assert_eq!(
+            r.service_unavailable().unwrap_err().code,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let ok: AppResult<i32> = Ok(1);
+        assert_eq!(ok.map_to(StatusCode::IM_A_TEAPOT).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_or_message() {
+        let r: AppResult<()> = Err(AppError::not_found());
+        let err = r.or_message("no such user").unwrap_err();
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "no such user");
+    }
+
+    #[test]
+    fn test_inspect_err_log() {
+        let r: AppResult<()> = Err(AppError::not_found());
+        let err = r.inspect_err_log().unwrap_err();
+
+        assert_eq!(err.code, StatusCode::NOT_FOUND);
+        assert_eq!(err.message, "Not Found");
+    }
+
+    #[test]
+    fn test_flatten_app() {
+        let ok: AppResult<AppResult<i32>> = Ok(Ok(1));
+        assert_eq!(ok.flatten_app().unwrap(), 1);
+
+        let inner: AppResult<AppResult<i32>> = Ok(Err(AppError::not_found()));
+        assert_eq!(inner.flatten_app().unwrap_err(), AppError::not_found());
+
+        let outer: AppResult<AppResult<i32>> = Err(AppError::new("join failed"));
+        assert_eq!(
+            outer.flatten_app().unwrap_err(),
+            AppError::new("join failed")
+        );
+    }
+
+    #[test]
+    fn test_into_app() {
+        let r: Result<(), String> = Err("nope".to_string());
+        let err = r.into_app(StatusCode::BAD_REQUEST).unwrap_err();
+
+        assert_eq!(err, AppError::bad_request("nope"));
+
+        let r: Result<(), &str> = Err("boom");
+        assert_eq!(
+            r.into_app_500().unwrap_err(),
+            AppError::server_error("boom")
+        );
+
+        let ok: Result<i32, &str> = Ok(1);
+        assert_eq!(ok.into_app_500().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_require() {
+        assert_eq!(Some(1).require().unwrap(), 1);
+        assert_eq!(None::<i32>.require().unwrap_err(), AppError::not_found());
+
+        let err = None::<i32>
+            .require_or(StatusCode::GONE, "user deleted")
+            .unwrap_err();
+        assert_eq!(err.code, StatusCode::GONE);
+        assert_eq!(err.message, "user deleted");
+    }
+}
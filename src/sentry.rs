@@ -0,0 +1,114 @@
+use sentry_core::protocol::Value;
+
+use crate::AppError;
+
+impl AppError {
+    /// Send 5xx errors to Sentry, with the source chain, the error code and request id as tags
+    /// and the fields as extras. Client errors are left alone.
+    pub(crate) fn capture(&self) {
+        if !self.is_server_error() {
+            return;
+        }
+
+        sentry_core::with_scope(
+            |scope| {
+                scope.set_tag("status", self.code.as_u16());
+
+                if let Some(id) = self.code_id() {
+                    scope.set_tag("error_code", id);
+                }
+
+                if let Some(id) = self.request_id() {
+                    scope.set_tag("request_id", id);
+                }
+
+                for (key, value) in self.fields() {
+                    scope.set_extra(key, Value::from(value.as_str()));
+                }
+            },
+            || sentry_core::capture_error(self),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let events = sentry_core::test::with_captured_events(|| {
+            AppError::not_found().capture();
+            AppError::new("db down")
+                .with_code_id("DB_DOWN")
+                .with_request_id("abc123")
+                .with_field("pool", "main")
+                .capture();
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tags["error_code"], "DB_DOWN");
+        assert_eq!(events[0].tags["status"], "500");
+        assert_eq!(events[0].extra["pool"], "main");
+        assert_eq!(events[0].tags["request_id"], "abc123");
+    }
+
+    #[test]
+    fn test_request_id_middleware_tag() {
+        use axum::body::Body;
+        use axum::extract::Request;
+        use axum::routing::get;
+        use axum::Router;
+        use tower::ServiceExt;
+
+        async fn handler() -> Result<(), AppError> {
+            Err(AppError::new("db down"))
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(axum::middleware::from_fn(crate::middleware::request_id));
+        let req = Request::builder()
+            .uri("/")
+            .header(crate::middleware::X_REQUEST_ID, "abc123")
+            .body(Body::empty())
+            .unwrap();
+
+        let events = sentry_core::test::with_captured_events(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(app.oneshot(req))
+                .unwrap();
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tags["request_id"], "abc123");
+    }
+
+    #[test]
+    fn test_capture_app_errors() {
+        use axum::response::IntoResponse;
+
+        let events = sentry_core::test::with_captured_events(|| {
+            let errors: crate::AppErrors = vec![AppError::bad_request("a"), AppError::new("b")]
+                .into_iter()
+                .collect();
+            let _ = errors.into_response();
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tags["status"], "500");
+    }
+
+    #[test]
+    fn test_capture_explicit_renderers() {
+        let events = sentry_core::test::with_captured_events(|| {
+            let _ = AppError::new("a").into_json_response();
+            let _ = AppError::new("b").into_html_response();
+            let _ = AppError::new("c").into_problem_details();
+        });
+
+        assert_eq!(events.len(), 3);
+    }
+}
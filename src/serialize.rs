@@ -0,0 +1,70 @@
+use axum::http::StatusCode;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::AppError;
+
+/// The serialized form of an `AppError`, with the code as a plain `u16`.
+#[derive(Serialize, Deserialize)]
+struct SerializedError {
+    code: u16,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<String>,
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedError {
+            code: self.code.as_u16(),
+            message: self.message.to_string(),
+            error_code: self.code_id().map(str::to_string),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedError::deserialize(deserializer)?;
+        let code = StatusCode::from_u16(serialized.code).map_err(D::Error::custom)?;
+
+        let mut err = AppError::raw(code, serialized.message);
+        err.location = None;
+
+        if let Some(id) = serialized.error_code {
+            err = err.with_code_id(id);
+        }
+
+        Ok(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let err = AppError::not_found().with_code_id("USER_MISSING");
+        let json = serde_json::to_string(&err).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"code":404,"message":"Not Found","error_code":"USER_MISSING"}"#
+        );
+
+        let back: AppError = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.code, StatusCode::NOT_FOUND);
+        assert_eq!(back.message, "Not Found");
+        assert_eq!(back.code_id(), Some("USER_MISSING"));
+        assert!(back.location().is_none());
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let result = serde_json::from_str::<AppError>(r#"{"code":1000,"message":"hi"}"#);
+
+        assert!(result.is_err());
+    }
+}
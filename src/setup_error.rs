@@ -1,8 +1,12 @@
+use std::env::VarError;
+use std::error::Error;
 use std::fmt::Display;
 
 #[derive(Debug)]
 pub struct SetupError {
     pub msg: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+    exit_code: i32,
 }
 
 impl Display for SetupError {
@@ -11,12 +15,163 @@ impl Display for SetupError {
     }
 }
 
+impl Error for SetupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
 impl SetupError {
     pub fn new(msg: impl ToString) -> Self {
         Self {
             msg: msg.to_string(),
+            source: None,
+            exit_code: 1,
         }
     }
+
+    /// Create a new `SetupError` which keeps the underlying error around as its `source`.
+    pub fn with_source(
+        msg: impl ToString,
+        source: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            msg: msg.to_string(),
+            source: Some(source.into()),
+            exit_code: 1,
+        }
+    }
+
+    /// Set the code `report_and_exit` exits the process with. Defaults to 1.
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = code;
+        self
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    /// Print the error to stderr and exit the process with its exit code.
+    pub fn report_and_exit(self) -> ! {
+        eprint!("{}", self);
+        std::process::exit(self.exit_code)
+    }
+}
+
+/// Lets `std::env::var("DATABASE_URL")?` be used in setup code.
+impl From<VarError> for SetupError {
+    fn from(err: VarError) -> Self {
+        let msg = match err {
+            VarError::NotPresent => "Environment variable is not set",
+            VarError::NotUnicode(_) => "Environment variable is not valid unicode",
+        };
+
+        Self::with_source(msg, err)
+    }
 }
 
 pub type SetupResult = Result<(), SetupError>;
+
+/// Collects the failures of several independent setup steps so they can be reported together.
+#[derive(Debug, Default)]
+pub struct SetupErrors {
+    errors: Vec<SetupError>,
+}
+
+impl Display for SetupErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for err in &self.errors {
+            write!(f, "{}", err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for SetupErrors {}
+
+impl SetupErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, err: impl Into<SetupError>) {
+        self.errors.push(err.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Ok` if nothing failed. A single failure is returned as is, several are combined into one
+    /// `SetupError` with all the messages, which keeps the collected errors as its `source`.
+    pub fn into_result(mut self) -> SetupResult {
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => Err(self.errors.remove(0)),
+            _ => {
+                let msg = self
+                    .errors
+                    .iter()
+                    .map(|err| err.msg.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+
+                Err(SetupError::with_source(msg, self))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_var_error() {
+        fn setup() -> SetupResult {
+            std::env::var("WHYNOT_ERRORS_SURELY_UNSET")?;
+            Ok(())
+        }
+
+        let err = setup().unwrap_err();
+        assert_eq!(err.msg, "Environment variable is not set");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(SetupError::new("no config").exit_code(), 1);
+        assert_eq!(
+            SetupError::new("port in use")
+                .with_exit_code(69)
+                .exit_code(),
+            69
+        );
+    }
+
+    #[test]
+    fn test_setup_errors() {
+        let mut errors = SetupErrors::new();
+        assert!(errors.is_empty());
+
+        errors.push(SetupError::new("DATABASE_URL is not set"));
+        errors.push(SetupError::new("could not bind to port 80"));
+        assert_eq!(
+            errors.to_string(),
+            "Setup Error: DATABASE_URL is not set\nSetup Error: could not bind to port 80\n"
+        );
+
+        let err = errors.into_result().unwrap_err();
+        assert_eq!(
+            err.msg,
+            "DATABASE_URL is not set; could not bind to port 80"
+        );
+        assert!(err.source().is_some());
+
+        assert!(SetupErrors::new().into_result().is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+//! Assertions for tests of handlers which return `AppError`s.
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::Response;
+
+/// Check that `response` is an error with the given code, whose message contains
+/// `message_contains`. Works with plain text, JSON and problem details bodies.
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use axum::response::IntoResponse;
+/// use whynot_errors::test_util::assert_app_error;
+/// use whynot_errors::AppError;
+///
+/// async fn test_missing_name() {
+///     let response = AppError::bad_request("name is required").json().into_response();
+///     assert_app_error(response, StatusCode::BAD_REQUEST, "required").await;
+/// }
+/// ```
+pub async fn assert_app_error(response: Response, code: StatusCode, message_contains: &str) {
+    assert_eq!(response.status(), code, "unexpected status code");
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read the response body");
+
+    let message = if content_type.starts_with("application/json")
+        || content_type.starts_with("application/problem+json")
+    {
+        let body: serde_json::Value =
+            serde_json::from_slice(&body).expect("response body is not valid JSON");
+
+        body.get("error")
+            .or_else(|| body.get("detail"))
+            .and_then(|message| message.as_str())
+            .expect("JSON body has no error message")
+            .to_string()
+    } else {
+        String::from_utf8_lossy(&body).into_owned()
+    };
+
+    assert!(
+        message.contains(message_contains),
+        "error message {message:?} does not contain {message_contains:?}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppError;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_assert_app_error() {
+        assert_app_error(
+            AppError::not_found().into_response(),
+            StatusCode::NOT_FOUND,
+            "Not",
+        )
+        .await;
+
+        assert_app_error(
+            AppError::conflict().into_problem_details(),
+            StatusCode::CONFLICT,
+            "Conflict",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "does not contain")]
+    async fn test_assert_app_error_message() {
+        assert_app_error(
+            AppError::not_found().json().into_response(),
+            StatusCode::NOT_FOUND,
+            "user",
+        )
+        .await;
+    }
+}
@@ -0,0 +1,47 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::AppResult;
+
+/// Run `future`, failing with a 504 if it takes longer than `duration`.
+///
+/// ```
+/// use std::time::Duration;
+/// use whynot_errors::{with_timeout, AppResult};
+///
+/// async fn fetch_user() -> AppResult<String> {
+///     Ok("ada".to_string())
+/// }
+///
+/// async fn handler() -> AppResult<String> {
+///     with_timeout(Duration::from_secs(2), fetch_user()).await
+/// }
+/// ```
+pub async fn with_timeout<T>(
+    duration: Duration,
+    future: impl Future<Output = AppResult<T>>,
+) -> AppResult<T> {
+    tokio::time::timeout(duration, future).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppError;
+    use axum::http::StatusCode;
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        let ok = with_timeout(Duration::from_secs(1), async { Ok(1) }).await;
+        assert_eq!(ok.unwrap(), 1);
+
+        let inner = with_timeout(Duration::from_secs(1), async {
+            Err::<(), _>(AppError::not_found())
+        })
+        .await;
+        assert_eq!(inner.unwrap_err(), AppError::not_found());
+
+        let slow = with_timeout(Duration::ZERO, std::future::pending::<AppResult<()>>()).await;
+        assert_eq!(slow.unwrap_err().code, StatusCode::GATEWAY_TIMEOUT);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::AppError;
+
+/// Trailer carrying the status code of an error which happened mid-stream.
+pub const ERROR_STATUS: HeaderName = HeaderName::from_static("error-status");
+
+/// Trailer carrying the percent-encoded public message of an error which happened mid-stream.
+pub const ERROR_MESSAGE: HeaderName = HeaderName::from_static("error-message");
+
+/// Trailer carrying the `with_code_id` identifier of an error which happened mid-stream.
+pub const ERROR_CODE: HeaderName = HeaderName::from_static("error-code");
+
+impl AppError {
+    /// Convert into HTTP trailers, in the spirit of `grpc-status`, for streaming responses which
+    /// fail after the status line and headers were sent. Send them as the last frame of the body,
+    /// with `http_body::Frame::trailers`, and announce them with a `Trailer` header up front.
+    pub fn into_trailers(self) -> HeaderMap {
+        let mut trailers = HeaderMap::new();
+
+        trailers.insert(ERROR_STATUS, HeaderValue::from(self.code.as_u16()));
+
+        if let Ok(message) = HeaderValue::from_str(&percent_encode(&self.public_message())) {
+            trailers.insert(ERROR_MESSAGE, message);
+        }
+
+        if let Some(id) = self.code_id().and_then(|id| HeaderValue::from_str(id).ok()) {
+            trailers.insert(ERROR_CODE, id);
+        }
+
+        trailers
+    }
+}
+
+/// Percent-encode everything outside of printable ASCII, plus `%` itself.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+
+    for byte in s.bytes() {
+        match byte {
+            b'%' => encoded.push_str("%25"),
+            b' '..=b'~' => encoded.push(byte as char),
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_into_trailers() {
+        let trailers = AppError::code(StatusCode::BAD_GATEWAY)("upstream died: 100% ✗")
+            .with_code_id("UPSTREAM")
+            .into_trailers();
+
+        assert_eq!(trailers[ERROR_STATUS], "502");
+        assert_eq!(trailers[ERROR_MESSAGE], "upstream died: 100%25 %E2%9C%97");
+        assert_eq!(trailers[ERROR_CODE], "UPSTREAM");
+    }
+}
@@ -0,0 +1,50 @@
+use axum::extract::ws::{close_code, CloseFrame};
+use axum::http::StatusCode;
+
+use crate::app_error::truncate;
+use crate::AppError;
+
+/// The longest reason a close frame can carry, in bytes.
+const MAX_REASON_LEN: usize = 123;
+
+impl AppError {
+    /// Convert into a WebSocket close frame. Client errors close with 1008 (policy violation),
+    /// a 503 with 1013 (try again later) and any other server error with 1011 (internal error).
+    /// The message is truncated to fit in the frame.
+    pub fn into_close_frame(self) -> CloseFrame {
+        let code = match self.code {
+            StatusCode::SERVICE_UNAVAILABLE => close_code::AGAIN,
+            code if code.is_server_error() => close_code::ERROR,
+            code if code.is_client_error() => close_code::POLICY,
+            _ => close_code::NORMAL,
+        };
+
+        CloseFrame {
+            code,
+            reason: truncate(&self.public_message(), MAX_REASON_LEN).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_code() {
+        assert_eq!(AppError::forbidden().into_close_frame().code, 1008);
+        assert_eq!(AppError::new("boom").into_close_frame().code, 1011);
+
+        let frame = AppError::code(StatusCode::SERVICE_UNAVAILABLE)("down").into_close_frame();
+        assert_eq!(frame.code, 1013);
+        assert_eq!(frame.reason.as_str(), "down");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let long = "é".repeat(100);
+        let frame = AppError::bad_request(&long).into_close_frame();
+
+        assert_eq!(frame.reason.len(), 122);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+
+use crate::problem::Problem;
+use crate::{escape_html, AppError};
+
+/// Content type for RFC 7807 XML bodies.
+pub const PROBLEM_XML: &str = "application/problem+xml";
+
+impl AppError {
+    /// Convert into an `application/problem+xml` response, with the same fields as
+    /// `into_problem_details`, field errors included.
+    pub fn into_problem_xml(self) -> Response {
+        self.respond_with(Self::problem_xml_response)
+    }
+
+    pub(crate) fn problem_xml_response(&self) -> Response {
+        (
+            self.code,
+            self.response_headers(),
+            [(CONTENT_TYPE, PROBLEM_XML)],
+            self.problem().to_xml(),
+        )
+            .into_response()
+    }
+}
+
+impl Problem<'_> {
+    fn to_xml(&self) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str(r#"<problem xmlns="urn:ietf:rfc:7807">"#);
+
+        let _ = write!(
+            xml,
+            "<type>{}</type><title>{}</title><status>{}</status><detail>{}</detail>",
+            escape_html(self.problem_type),
+            escape_html(self.title),
+            self.status,
+            escape_html(&self.detail),
+        );
+
+        if let Some(instance) = self.instance {
+            let _ = write!(xml, "<instance>{}</instance>", escape_html(instance));
+        }
+
+        if let Some(id) = self.request_id {
+            let _ = write!(xml, "<request_id>{}</request_id>", escape_html(id));
+        }
+
+        // Arrays are written as `i` elements, as in the XML format of RFC 7807.
+        if !self.errors.is_empty() {
+            xml.push_str("<errors>");
+
+            for error in &self.errors {
+                let _ = write!(
+                    xml,
+                    "<i><field>{}</field><message>{}</message></i>",
+                    escape_html(error.field),
+                    escape_html(error.message),
+                );
+            }
+
+            xml.push_str("</errors>");
+        }
+
+        xml.push_str("</problem>");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_problem_xml() {
+        let err = AppError::code(StatusCode::CONFLICT)("<email> taken").with_instance("/users/1");
+
+        assert_eq!(
+            err.problem().to_xml(),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<problem xmlns="urn:ietf:rfc:7807">"#,
+                "<type>about:blank</type><title>Conflict</title><status>409</status>",
+                "<detail>&lt;email&gt; taken</detail><instance>/users/1</instance>",
+                "</problem>",
+            )
+        );
+
+        let res = err.into_problem_xml();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(res.headers()[CONTENT_TYPE], PROBLEM_XML);
+    }
+
+    #[test]
+    fn test_problem_xml_errors() {
+        let err = AppError::unprocessable_entity("invalid")
+            .with_field_error("email", "<required>")
+            .with_field_error("name", "too short");
+
+        assert!(err.problem().to_xml().ends_with(concat!(
+            "<errors>",
+            "<i><field>email</field><message>&lt;required&gt;</message></i>",
+            "<i><field>name</field><message>too short</message></i>",
+            "</errors></problem>",
+        )));
+    }
+}